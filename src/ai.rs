@@ -0,0 +1,337 @@
+//! The built-in pilot. It shoots asteroids, and in versus the other ships,
+//! where they are going to be; it steers clear of whatever is about to hit
+//! it and jumps to hyperspace when there is no way out.
+
+use crate::control::{Observation, ShipController};
+use crate::input::ShipInput;
+use crate::math::Vec2;
+use crate::settings::Mode;
+use crate::shape::Shape;
+use crate::world::World;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+// Frames ahead the pilot looks for collisions.
+const HORIZON: f64 = 90.0;
+// Collisions sooner than this are dodged rather than shot at.
+const EVADE: f64 = 40.0;
+// Sooner than this there is no dodging; time for hyperspace.
+const TRAPPED: f64 = 8.0;
+// Room the pilot keeps around itself, in pixels.
+const MARGIN: f64 = 10.0;
+// Furthest a shot may travel to its target, in frames.
+const RANGE: f64 = 110.0;
+// Turn speed change per frame and turn per unit of it, as in
+// `Shape::direct`.
+const TURN_STEP: f64 = 0.0625;
+const TURN_RATE: f64 = 0.1;
+
+/// Flies a ship on its own; see the module docs.
+#[derive(Default)]
+pub struct Pilot;
+impl ShipController for Pilot {
+    fn command(&mut self, observation: &Observation) -> ShipInput {
+        let ship = observation.own();
+        if !ship.alive {
+            return ShipInput::default();
+        }
+        let world = observation.world;
+        let me = &ship.shape;
+        let threat = threats(observation)
+            .filter(|threat| threat.time >= ship.shield as f64)
+            .min_by(|a, b| a.time.total_cmp(&b.time));
+        if let Some(threat) = threat.filter(|threat| threat.time < EVADE && !world.god) {
+            if threat.time < TRAPPED && ship.hyperspace == 0 {
+                return ShipInput {
+                    hyperspace: true,
+                    ..ShipInput::default()
+                };
+            }
+            return evade(me, &threat);
+        }
+        match target(observation) {
+            Some(aim) => attack(me, &aim, ship.fire_delay == 0),
+            // Nothing in range: turn slowly on the spot to look around.
+            None => ShipInput {
+                right: me.s_rot < 0.5,
+                ..ShipInput::default()
+            },
+        }
+    }
+}
+
+/// Replaces the inputs of `ships` in `inputs` with the pilot's commands, for
+/// games that mix players and computer pilots.
+pub fn take_over(world: &World, inputs: &mut [ShipInput], ships: impl IntoIterator<Item = usize>) {
+    for ship in ships {
+        if let Some(input) = inputs.get_mut(ship) {
+            *input = Pilot.command(&Observation { world, ship });
+        }
+    }
+}
+
+// Something on course to hit the ship `time` frames from now, `miss` being
+// where it will be then relative to the ship.
+struct Threat {
+    time: f64,
+    miss: Vec2,
+    velocity: Vec2,
+}
+
+// Where to aim, and whether a shot that way would hit.
+struct Aim {
+    heading: f64,
+    tolerance: f64,
+    time: f64,
+}
+
+// Everything that can destroy the ship and is on course to: asteroids and,
+// in versus, the other ships' bullets. Both sides keep their current
+// velocity.
+fn threats<'a>(observation: &'a Observation) -> impl Iterator<Item = Threat> + 'a {
+    let world = observation.world;
+    let me = &observation.own().shape;
+    let bullets = world
+        .bullets
+        .values()
+        .filter(move |bullet| world.rules.mode == Mode::Versus && bullet.owner != observation.ship);
+    let asteroids = world
+        .asteroids
+        .values()
+        .map(|asteroid| (asteroid, asteroid.scale * 0.8));
+    asteroids
+        .chain(bullets.map(|bullet| (bullet, 0.0)))
+        .filter_map(move |(shape, reach)| {
+            let offset = offset(world, me, shape);
+            let velocity = shape.velocity() - me.velocity();
+            let radius = reach + me.scale * 0.8 + MARGIN;
+            let time = collision_time(offset, velocity, radius)?;
+            Some(Threat {
+                time,
+                miss: offset + velocity * time,
+                velocity,
+            })
+        })
+}
+
+// From `me` to `shape`, across the edges if `shape` wraps.
+fn offset(world: &World, me: &Shape, shape: &Shape) -> Vec2 {
+    if shape.bound {
+        me.pos.wrapped_delta(shape.pos, world.bounds)
+    } else {
+        shape.pos - me.pos
+    }
+}
+
+// When something at `offset` moving at `velocity` first comes within
+// `radius`, if it does within the horizon.
+fn collision_time(offset: Vec2, velocity: Vec2, radius: f64) -> Option<f64> {
+    let c = offset.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let a = velocity.length_squared();
+    let b = 2.0 * offset.dot(velocity);
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=HORIZON).contains(&time).then_some(time)
+}
+
+// Flies out of the threat's path: sideways to it, away from where it will
+// pass, forwards or backwards whichever is nearer.
+fn evade(me: &Shape, threat: &Threat) -> ShipInput {
+    let away = if threat.miss.length() > 1.0 {
+        -threat.miss
+    } else {
+        // Head on: either side will do.
+        threat.velocity.rotate(FRAC_PI_2)
+    };
+    let forwards = angle_between(me.rot, away.angle()).abs() < FRAC_PI_2;
+    let heading = if forwards {
+        away.angle()
+    } else {
+        away.angle() + PI
+    };
+    ShipInput {
+        thrust: forwards,
+        reverse: !forwards,
+        ..turn_towards(me, heading)
+    }
+}
+
+// The target a shot reaches soonest: asteroids, and in versus the other
+// ships, counting them as nearer. Shots do not cross the edges, so the
+// meeting point must lie inside the playfield.
+fn target(observation: &Observation) -> Option<Aim> {
+    let world = observation.world;
+    let me = &observation.own().shape;
+    let speed = me.scale * 0.6;
+    let ships = world
+        .ships
+        .iter()
+        .enumerate()
+        .filter(|(i, ship)| {
+            world.rules.mode == Mode::Versus
+                && *i != observation.ship
+                && ship.alive
+                && ship.shield == 0
+        })
+        .map(|(_, ship)| (&ship.shape, 0.5));
+    let asteroids = world.asteroids.values().map(|asteroid| (asteroid, 1.0));
+    asteroids
+        .chain(ships)
+        .filter_map(|(shape, weight)| {
+            let offset = offset(world, me, shape);
+            let time = intercept(offset, shape.velocity(), speed)?;
+            let meet = offset + shape.velocity() * time;
+            let at = me.pos + meet;
+            let inside =
+                at.x >= 0.0 && at.y >= 0.0 && at.x <= world.bounds.x && at.y <= world.bounds.y;
+            (inside && time <= RANGE).then(|| Aim {
+                heading: meet.angle(),
+                tolerance: (shape.scale * 0.6 / meet.length().max(1.0)).atan(),
+                time: time * weight,
+            })
+        })
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
+// When a shot at `speed` meets something at `offset` moving at `velocity`.
+fn intercept(offset: Vec2, velocity: Vec2, speed: f64) -> Option<f64> {
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2.0 * offset.dot(velocity);
+    let c = offset.length_squared();
+    if a.abs() < 1e-9 {
+        return (b < 0.0).then(|| -c / b);
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        .into_iter()
+        .filter(|time| *time > 0.0)
+        .min_by(f64::total_cmp)
+}
+
+// Turns towards the aim and fires when lined up and loaded; closes in on
+// targets that are still far off.
+fn attack(me: &Shape, aim: &Aim, loaded: bool) -> ShipInput {
+    let off = angle_between(heading_next(me), aim.heading).abs();
+    ShipInput {
+        fire: loaded && off < aim.tolerance,
+        thrust: aim.time > RANGE / 2.0 && off < 0.3 && me.s < 0.5,
+        ..turn_towards(me, aim.heading)
+    }
+}
+
+// Turns towards `heading`, letting go early so the turn stops on it.
+fn turn_towards(me: &Shape, heading: f64) -> ShipInput {
+    let off = angle_between(me.rot, heading);
+    // How far the ship still turns if it lets go now.
+    let frames = (me.s_rot.abs() / TURN_STEP).floor();
+    let coast = me.s_rot * TURN_RATE * (frames + 1.0) / 2.0;
+    let turn = off - coast;
+    if turn.abs() < TURN_STEP * TURN_RATE {
+        return ShipInput::default();
+    }
+    ShipInput {
+        left: turn < 0.0,
+        right: turn > 0.0,
+        ..ShipInput::default()
+    }
+}
+
+// The heading after the ship's next turn step, which is what a shot fired
+// now leaves at.
+fn heading_next(me: &Shape) -> f64 {
+    me.rot + me.s_rot * TURN_RATE
+}
+
+// Signed angle from `from` to `to`, within half a turn either way.
+fn angle_between(from: f64, to: f64) -> f64 {
+    (to - from + PI).rem_euclid(2.0 * PI) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::commands;
+    use crate::render::Color;
+    use crate::shape::ShapeType;
+    use crate::world::PLAYFIELD;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn asteroid(pos: Vec2, rot: f64, s: f64, scale: f64) -> Shape {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut asteroid = Shape::new(
+            pos,
+            rot,
+            scale,
+            Color::WHITE,
+            ShapeType::Asteroid(0.0),
+            &mut rng,
+        );
+        asteroid.s = s;
+        asteroid.set_bound();
+        asteroid
+    }
+
+    fn fly(world: &mut World, frames: u64) {
+        let mut pilots: Vec<Box<dyn ShipController>> = vec![Box::new(Pilot)];
+        for _ in 0..frames {
+            let inputs = commands(world, &mut pilots);
+            world.step(&inputs);
+        }
+    }
+
+    #[test]
+    fn shoots_a_passing_asteroid() {
+        let mut world = World::new(PLAYFIELD, 1);
+        let start = world.ships[0].shape.pos;
+        world
+            .asteroids
+            .insert(asteroid(start + Vec2::new(200.0, -150.0), PI, 1.0, 30.0));
+        fly(&mut world, 120);
+        assert!(world.ships[0].alive);
+        assert!(world.score() > 0);
+    }
+
+    #[test]
+    fn dodges_what_would_hit_it() {
+        let mut world = World::new(PLAYFIELD, 1);
+        let start = world.ships[0].shape.pos;
+        // Coming straight at the ship from the side, fast.
+        world
+            .asteroids
+            .insert(asteroid(start - Vec2::new(260.0, 0.0), 0.0, 4.0, 40.0));
+        let mut idle = world.clone();
+        for _ in 0..150 {
+            idle.step(&[ShipInput::default()]);
+        }
+        assert!(!idle.ships[0].alive);
+        fly(&mut world, 150);
+        assert!(world.ships[0].alive);
+    }
+
+    #[test]
+    fn jumps_when_trapped() {
+        let mut world = World::new(PLAYFIELD, 1);
+        let start = world.ships[0].shape.pos;
+        for angle in [0.0, FRAC_PI_2, PI, -FRAC_PI_2] {
+            let from = start + Vec2::from_angle(angle) * 70.0;
+            world
+                .asteroids
+                .insert(asteroid(from, angle + PI, 2.0, 40.0));
+        }
+        let input = Pilot.command(&Observation {
+            world: &world,
+            ship: 0,
+        });
+        assert!(input.hyperspace);
+    }
+}
@@ -0,0 +1,273 @@
+//! Entity storage with stable handles.
+
+/// Stable reference to an entity in an `Arena`. A handle stays valid until the
+/// entity it points at is removed; after that it never matches again, even if
+/// the slot is reused.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Clone)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Generational arena. Entities can be inserted and removed immediately, or
+/// queued with `spawn`/`despawn` while the arena is being iterated and applied
+/// together by `flush`.
+#[derive(Clone)]
+pub struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+    spawn_queue: Vec<T>,
+    despawn_queue: Vec<Handle>,
+}
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            spawn_queue: Vec::new(),
+            despawn_queue: Vec::new(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn insert(&mut self, value: T) -> Handle {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            Handle {
+                index,
+                generation: slot.generation,
+            }
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                value: Some(value),
+            });
+            Handle {
+                index: self.slots.len() as u32 - 1,
+                generation: 0,
+            }
+        }
+    }
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation || slot.value.is_none() {
+            return None;
+        }
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+        slot.value.take()
+    }
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| {
+                (
+                    Handle {
+                        index: index as u32,
+                        generation: slot.generation,
+                    },
+                    value,
+                )
+            })
+        })
+    }
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+    /// Snapshot of the live handles, for loops that queue spawns or despawns
+    /// while they walk the arena.
+    pub fn handles(&self) -> Vec<Handle> {
+        self.iter().map(|(handle, _)| handle).collect()
+    }
+    /// Removes every entity for which `keep` returns false, right away.
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        for handle in self.handles() {
+            if self.get(handle).is_some_and(|value| !keep(value)) {
+                self.remove(handle);
+            }
+        }
+    }
+    /// Removes everything, invalidating every handle given out so far.
+    pub fn clear(&mut self) {
+        for handle in self.handles() {
+            self.remove(handle);
+        }
+        self.spawn_queue.clear();
+        self.despawn_queue.clear();
+    }
+    /// Every slot in order with its generation and value, occupied or not,
+    /// and the free slots in the order they will be reused. Together they
+    /// rebuild the arena with `from_slots`.
+    pub fn slots(&self) -> (Vec<(u32, Option<&T>)>, &[u32]) {
+        let slots = self
+            .slots
+            .iter()
+            .map(|slot| (slot.generation, slot.value.as_ref()))
+            .collect();
+        (slots, &self.free)
+    }
+    /// The arena `slots` described, with nothing queued.
+    pub fn from_slots(slots: Vec<(u32, Option<T>)>, free: Vec<u32>) -> Arena<T> {
+        let slots: Vec<Slot<T>> = slots
+            .into_iter()
+            .map(|(generation, value)| Slot { generation, value })
+            .collect();
+        Arena {
+            len: slots.iter().filter(|slot| slot.value.is_some()).count(),
+            slots,
+            free,
+            spawn_queue: Vec::new(),
+            despawn_queue: Vec::new(),
+        }
+    }
+    /// Queues `value` to be inserted on the next `flush`.
+    pub fn spawn(&mut self, value: T) {
+        self.spawn_queue.push(value);
+    }
+    /// Queues `handle` to be removed on the next `flush`. Queuing the same
+    /// handle twice is harmless.
+    pub fn despawn(&mut self, handle: Handle) {
+        if !self.despawning(handle) {
+            self.despawn_queue.push(handle);
+        }
+    }
+    pub fn despawning(&self, handle: Handle) -> bool {
+        self.despawn_queue.contains(&handle)
+    }
+    /// Applies queued despawns, then queued spawns, and returns the handles of
+    /// the newly spawned entities.
+    pub fn flush(&mut self) -> Vec<Handle> {
+        for handle in std::mem::take(&mut self.despawn_queue) {
+            self.remove(handle);
+        }
+        std::mem::take(&mut self.spawn_queue)
+            .into_iter()
+            .map(|value| self.insert(value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(a), Some(&"a"));
+        assert_eq!(arena.remove(a), Some("a"));
+        assert_eq!(arena.remove(a), None);
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get(b), Some(&"b"));
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn stale_handles_do_not_match_reused_slots() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        arena.remove(a);
+        let b = arena.insert(2);
+        assert_ne!(a, b);
+        assert!(!arena.contains(a));
+        assert_eq!(arena.get(b), Some(&2));
+        assert!(arena.get_mut(a).is_none());
+    }
+
+    #[test]
+    fn deferred_spawn_and_despawn() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        for value in arena.values_mut() {
+            *value *= 10;
+        }
+        arena.despawn(b);
+        arena.despawn(b);
+        arena.spawn(3);
+        assert!(arena.despawning(b));
+        assert_eq!(arena.len(), 2);
+        let spawned = arena.flush();
+        assert_eq!(spawned.len(), 1);
+        assert!(!arena.contains(b));
+        assert_eq!(arena.get(a), Some(&10));
+        assert_eq!(arena.get(spawned[0]), Some(&3));
+        assert_eq!(arena.len(), 2);
+        assert!(arena.flush().is_empty());
+    }
+
+    #[test]
+    fn handles_allow_queueing_while_walking() {
+        let mut arena = Arena::new();
+        for value in 0..5 {
+            arena.insert(value);
+        }
+        for handle in arena.handles() {
+            let value = *arena.get(handle).unwrap();
+            if value % 2 == 0 {
+                arena.despawn(handle);
+                arena.spawn(value + 10);
+            }
+        }
+        arena.flush();
+        let mut values: Vec<i32> = arena.values().copied().collect();
+        values.sort();
+        assert_eq!(values, vec![1, 3, 10, 12, 14]);
+        arena.retain(|value| *value < 10);
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn clear_invalidates_handles() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        arena.spawn(2);
+        arena.clear();
+        assert!(arena.is_empty());
+        assert!(arena.flush().is_empty());
+        let b = arena.insert(3);
+        assert!(!arena.contains(a));
+        assert_eq!(arena.values().copied().collect::<Vec<_>>(), vec![3]);
+        assert!(arena.contains(b));
+    }
+}
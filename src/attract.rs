@@ -0,0 +1,60 @@
+//! The title screen: the built-in pilot plays a demo game behind the name
+//! until someone presses a key.
+
+use crate::ai::Pilot;
+use crate::control::{commands, ShipController};
+use crate::font::draw_text;
+use crate::math::Vec2;
+use crate::render::{Color, Renderer};
+use crate::settings::Rules;
+use crate::world::World;
+
+const TITLE: &str = "FOXTROIDS";
+const PROMPT: &str = "PRESS FIRE TO PLAY";
+// A demo game is cut short after a minute and the next one starts.
+const DEMO_FRAMES: u64 = 60 * 60;
+
+/// A game the pilot plays by itself, starting over with the next seed when
+/// it ends.
+pub struct Demo {
+    pub world: World,
+    pilots: Vec<Box<dyn ShipController>>,
+}
+impl Demo {
+    pub fn new(rules: Rules, seed: u64) -> Demo {
+        let pilots = (0..rules.players)
+            .map(|_| Box::new(Pilot) as Box<dyn ShipController>)
+            .collect();
+        Demo {
+            world: World::with_rules(rules, seed),
+            pilots,
+        }
+    }
+    pub fn step(&mut self) {
+        if self.world.over() || self.world.frame >= DEMO_FRAMES {
+            let seed = self.world.seed.wrapping_add(1);
+            self.world = World::with_rules(self.world.rules.clone(), seed);
+        }
+        let inputs = commands(&self.world, &mut self.pilots);
+        self.world.step(&inputs);
+    }
+    /// The demo game with the title over it.
+    pub fn draw(&self, r: &mut dyn Renderer) {
+        self.world.draw(r);
+        let view = self.world.camera.view;
+        let centred =
+            |text: &str, y: f64, size: f64| Vec2::new((view.x - text.len() as f64 * size) / 2.0, y);
+        draw_text(
+            TITLE,
+            centred(TITLE, view.y * 0.3, 40.0),
+            40.0,
+            Color::WHITE,
+            r,
+        );
+        // The prompt blinks, half a second on and half off.
+        if (self.world.frame / 30).is_multiple_of(2) {
+            let y = view.y * 0.3 + 70.0;
+            draw_text(PROMPT, centred(PROMPT, y, 12.0), 12.0, Color::WHITE, r);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Sound generation.
+
+/// Square wave oscillator used for the shooting sound.
+#[derive(PartialEq, Debug)]
+pub struct SquareWave {
+    /// Fraction of a period advanced per sample, `frequency / sample_rate`.
+    pub phase_inc: f32,
+    pub phase: f32,
+    pub volume: f32,
+}
+impl SquareWave {
+    pub fn new(frequency: f32, sample_rate: i32, volume: f32) -> SquareWave {
+        SquareWave {
+            phase_inc: frequency / sample_rate as f32,
+            phase: 0.7,
+            volume,
+        }
+    }
+    /// Writes the next `out.len()` samples.
+    pub fn fill(&mut self, out: &mut [f32]) {
+        // Generate a square wave
+        for x in out.iter_mut() {
+            *x = if self.phase <= 0.5 {
+                self.volume
+            } else {
+                -self.volume
+            };
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+#[cfg(feature = "audio")]
+impl sdl2::audio::AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.fill(out);
+    }
+}
@@ -0,0 +1,99 @@
+//! Serves `foxtroids::env` to learning agents over stdin and stdout, in the
+//! line-delimited JSON of `foxtroids::env::protocol`.
+
+use foxtroids::cli::{self, arg, fail};
+use foxtroids::control::Observation;
+use foxtroids::env::protocol::{self, Reply};
+use foxtroids::env::{Env, EnvConfig};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, BufRead, BufWriter, Write};
+
+// `--frame-skip N`, `--nearest N`, `--rays N`, `--pixels WxH` and
+// `--max-frames N`, on top of the rules from `--settings FILE` and the same
+// rule options the game takes.
+fn config(args: &[String]) -> EnvConfig {
+    let rules = cli::rules(args, cli::settings(args, None).rules, &cli::RULE_OPTIONS);
+    let defaults = EnvConfig::default();
+    let pixels = arg::<String>(args, "--pixels").map(|size| {
+        size.split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .unwrap_or_else(|| fail("--pixels needs WIDTHxHEIGHT, e.g. 84x84"))
+    });
+    EnvConfig {
+        rules,
+        frame_skip: arg(args, "--frame-skip").unwrap_or(defaults.frame_skip),
+        nearest: arg(args, "--nearest").unwrap_or(defaults.nearest),
+        rays: arg(args, "--rays").unwrap_or(defaults.rays),
+        pixels,
+        max_frames: arg(args, "--max-frames").unwrap_or(defaults.max_frames),
+    }
+}
+
+// What the agent's ship sees.
+fn ship(env: &Env) -> Observation<'_> {
+    Observation {
+        world: &env.world,
+        ship: 0,
+    }
+}
+
+// Answers one request on one line of `out`. False once the agent is done.
+fn answer(
+    env: &mut Env,
+    seed: &mut Option<u64>,
+    line: &str,
+    out: &mut impl Write,
+) -> io::Result<bool> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(error) => return reply(out, &protocol::error(error)),
+    };
+    match request["op"].as_str() {
+        Some("spec") => reply(out, &protocol::spec(&env.config)),
+        Some("reset") => {
+            let next = request["seed"]
+                .as_u64()
+                .unwrap_or_else(|| seed.map_or(0, |seed| seed.wrapping_add(1)));
+            *seed = Some(next);
+            let step = env.reset(next);
+            reply(out, &Reply::new(&ship(env), &step).reset())
+        }
+        Some("step") if seed.is_none() => reply(out, &protocol::error("reset before stepping")),
+        Some("step") => match protocol::action(&request["action"]) {
+            Ok(input) => {
+                let step = env.step(input);
+                reply(out, &Reply::new(&ship(env), &step))
+            }
+            Err(error) => reply(out, &protocol::error(error)),
+        },
+        Some("close") => Ok(false),
+        _ => reply(
+            out,
+            &protocol::error("op must be spec, reset, step or close"),
+        ),
+    }
+}
+
+fn reply(out: &mut impl Write, reply: &impl Serialize) -> io::Result<bool> {
+    protocol::send(out, reply)?;
+    Ok(true)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut env = Env::new(config(&args));
+    let mut seed = None;
+    let mut out = BufWriter::new(io::stdout().lock());
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap_or_else(|error| fail(format!("could not read: {}", error)));
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Stop when asked to, or when the agent hangs up.
+        if !answer(&mut env, &mut seed, &line, &mut out).unwrap_or(false) {
+            break;
+        }
+    }
+}
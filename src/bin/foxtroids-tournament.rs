@@ -0,0 +1,268 @@
+//! Plays bots against each other headless, every pair on every seed in every
+//! mode from both sides, and rates them; see `foxtroids::tournament`.
+//! Entrants are the arguments that are not options, each `[NAME=]KIND`:
+//!
+//!     pilot                  the built-in pilot
+//!     idle                   a ship nobody flies
+//!     genome:FILE            an evolved network; a FILE ending in .genome will do
+//!     exec:COMMAND           a program speaking the `foxtroids-env` protocol
+//!
+//! Ratings carry over in `--ratings FILE`, saved after every match.
+
+use foxtroids::ai::Pilot;
+use foxtroids::cli::{self, arg, fail};
+use foxtroids::control::{Idle, ShipController};
+use foxtroids::env::agent::Agent;
+use foxtroids::env::EnvConfig;
+use foxtroids::neuro::{Genome, NeuralPilot};
+use foxtroids::tournament::{play, Ratings, K};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Options taking a value; everything else is an entrant.
+const OPTIONS: [&str; 11] = [
+    "--seeds",
+    "--modes",
+    "--frames",
+    "--k",
+    "--ratings",
+    "--frame-skip",
+    "--nearest",
+    "--rays",
+    "--settings",
+    "--arena",
+    "--lives",
+];
+
+// A comma separated list after `name`.
+fn list<T: std::str::FromStr>(args: &[String], name: &str, default: &str) -> Vec<T> {
+    arg::<String>(args, name)
+        .unwrap_or(default.into())
+        .split(',')
+        .map(|item| {
+            item.trim()
+                .parse()
+                .unwrap_or_else(|_| fail(format!("bad {} item: {}", name, item)))
+        })
+        .collect()
+}
+
+// A file's name without its directory or extension.
+fn stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map_or(path.into(), |stem| stem.to_string_lossy().into())
+}
+
+// An entrant's name and controller from `[NAME=]KIND`.
+fn entrant(spec: &str, config: &EnvConfig) -> (String, Box<dyn ShipController>) {
+    let (name, kind) = match spec.split_once('=') {
+        Some((name, kind)) if !name.contains(':') => (Some(name.to_string()), kind),
+        _ => (None, spec),
+    };
+    let genome = |path: &str| -> Box<dyn ShipController> {
+        let genome = Genome::load(path)
+            .unwrap_or_else(|error| fail(format!("could not read {}: {}", path, error)));
+        Box::new(NeuralPilot(genome))
+    };
+    let (default, controller): (String, Box<dyn ShipController>) = match kind {
+        "pilot" => (kind.into(), Box::new(Pilot)),
+        "idle" => (kind.into(), Box::new(Idle)),
+        _ => match kind.split_once(':') {
+            Some(("genome", path)) => (stem(path), genome(path)),
+            Some(("exec", command)) => {
+                let agent = Agent::spawn(command, config.clone())
+                    .unwrap_or_else(|error| fail(format!("could not run {}: {}", command, error)));
+                let program = command.split_whitespace().last().unwrap_or(command);
+                (stem(program), Box::new(agent))
+            }
+            _ if kind.ends_with(".genome") => (stem(kind), genome(kind)),
+            _ => fail(format!(
+                "unknown entrant {}: use pilot, idle, genome:FILE or exec:COMMAND",
+                kind
+            )),
+        },
+    };
+    let name = name.unwrap_or(default);
+    // Ratings files take lines starting with `#` for comments.
+    if name.is_empty() || name.contains(char::is_whitespace) || name.starts_with('#') {
+        fail(format!(
+            "entrant names must be one word not starting with #, not \"{}\"",
+            name
+        ));
+    }
+    (name, controller)
+}
+
+// Two different items of `items`, both mutable.
+fn pair<T>(items: &mut [T], i: usize, j: usize) -> (&mut T, &mut T) {
+    if i < j {
+        let (left, right) = items.split_at_mut(j);
+        (&mut left[i], &mut right[0])
+    } else {
+        let (left, right) = items.split_at_mut(i);
+        (&mut right[0], &mut left[j])
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // Rules from `--settings FILE` and the rule options, always for two.
+    let mut rules = cli::rules(&args, cli::settings(&args, None).rules, &["arena", "lives"]);
+    let seeds: Vec<u64> = list(&args, "--seeds", "1,2,3");
+    let modes: Vec<String> = list(&args, "--modes", "coop,versus");
+    for mode in &modes {
+        rules
+            .clone()
+            .set("mode", mode)
+            .unwrap_or_else(|error| fail(error));
+    }
+    let frames = arg(&args, "--frames").unwrap_or(60 * 60);
+    let k = arg(&args, "--k").unwrap_or(K);
+    let path = arg(&args, "--ratings").unwrap_or(PathBuf::from("foxtroids.ratings"));
+    // How external agents observe: `--frame-skip N`, `--nearest N` and
+    // `--rays N`, as `foxtroids-env` takes them.
+    let defaults = EnvConfig::default();
+    let config = EnvConfig {
+        frame_skip: arg(&args, "--frame-skip").unwrap_or(defaults.frame_skip),
+        nearest: arg(&args, "--nearest").unwrap_or(defaults.nearest),
+        rays: arg(&args, "--rays").unwrap_or(defaults.rays),
+        ..defaults
+    };
+    let mut entrants: Vec<(String, Box<dyn ShipController>)> = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        if args[i].starts_with("--") {
+            if !OPTIONS.contains(&args[i].as_str()) {
+                fail(format!("unknown option {}", args[i]));
+            }
+            if i + 1 == args.len() {
+                fail(format!("{} needs a value", args[i]));
+            }
+            i += 2;
+            continue;
+        }
+        let (name, controller) = entrant(&args[i], &config);
+        if entrants.iter().any(|(other, _)| *other == name) {
+            fail(format!(
+                "two entrants are called {}; name them NAME=...",
+                name
+            ));
+        }
+        entrants.push((name, controller));
+        i += 1;
+    }
+    if entrants.len() < 2 {
+        fail("a tournament needs at least two entrants");
+    }
+    let mut ratings = if path.exists() {
+        Ratings::load(&path)
+            .unwrap_or_else(|error| fail(format!("could not read {}: {}", path.display(), error)))
+    } else {
+        Ratings::default()
+    };
+    let before = ratings.clone();
+
+    // Points each entrant scored in each mode on each seed, and in how many
+    // games.
+    let mut points: HashMap<(usize, &str, u64), (u32, u32)> = HashMap::new();
+    for mode in &modes {
+        rules.set("mode", mode).unwrap();
+        for &seed in &seeds {
+            for a in 0..entrants.len() {
+                for b in (0..entrants.len()).filter(|b| *b != a) {
+                    let (first, second) = pair(&mut entrants, a, b);
+                    let scored = play(&rules, seed, frames, [&mut *first.1, &mut *second.1]);
+                    println!(
+                        "{} seed {}: {} {} - {} {}",
+                        mode, seed, first.0, scored[0], scored[1], second.0
+                    );
+                    ratings.record(&first.0, &second.0, scored, k);
+                    for (entrant, scored) in [(a, scored[0]), (b, scored[1])] {
+                        let total = points.entry((entrant, mode, seed)).or_default();
+                        *total = (total.0 + scored, total.1 + 1);
+                    }
+                    if let Err(error) = ratings.save(&path) {
+                        fail(format!("could not save {}: {}", path.display(), error));
+                    }
+                }
+            }
+        }
+    }
+    ratings.rank();
+    if let Err(error) = ratings.save(&path) {
+        fail(format!("could not save {}: {}", path.display(), error));
+    }
+
+    // The leaderboard of this tournament's entrants, best first.
+    let mean = |entrant: usize, keys: &mut dyn Iterator<Item = (&str, u64)>| {
+        let (total, games) = keys.fold((0, 0), |sum, (mode, seed)| {
+            let (total, games) = points
+                .get(&(entrant, mode, seed))
+                .copied()
+                .unwrap_or_default();
+            (sum.0 + total, sum.1 + games)
+        });
+        total as f64 / games.max(1) as f64
+    };
+    let width = entrants
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    println!();
+    println!(
+        "{:>4}  {:<width$} {:>7} {:>7} {:>5} {:>5} {:>5} {:>9}",
+        "rank", "name", "rating", "change", "won", "drawn", "lost", "points"
+    );
+    let mut rank = 0;
+    for rating in &ratings.0 {
+        let Some(entrant) = entrants.iter().position(|(name, _)| *name == rating.name) else {
+            continue;
+        };
+        rank += 1;
+        let old = before
+            .0
+            .iter()
+            .find(|old| old.name == rating.name)
+            .cloned()
+            .unwrap_or_else(|| Ratings::default().get(&rating.name).clone());
+        let mut all = modes
+            .iter()
+            .flat_map(|mode| seeds.iter().map(move |seed| (mode.as_str(), *seed)));
+        println!(
+            "{:>4}  {:<width$} {:>7.1} {:>+7.1} {:>5} {:>5} {:>5} {:>9.1}",
+            rank,
+            rating.name,
+            rating.rating,
+            rating.rating - old.rating,
+            rating.wins - old.wins,
+            rating.draws - old.draws,
+            rating.losses - old.losses,
+            mean(entrant, &mut all)
+        );
+    }
+
+    // Mean points a game, by mode and seed.
+    println!();
+    print!("{:<width$}", "points");
+    for mode in &modes {
+        for seed in &seeds {
+            print!(" {:>12}", format!("{} {}", mode, seed));
+        }
+    }
+    println!();
+    for (entrant, (name, _)) in entrants.iter().enumerate() {
+        print!("{:<width$}", name);
+        for mode in &modes {
+            for &seed in &seeds {
+                print!(
+                    " {:>12.1}",
+                    mean(entrant, &mut std::iter::once((mode.as_str(), seed)))
+                );
+            }
+        }
+        println!();
+    }
+}
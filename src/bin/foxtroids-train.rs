@@ -0,0 +1,100 @@
+//! Evolves neural network pilots headless; see `foxtroids::neuro`. Every
+//! generation plays the same seeded games on all cores, saves a checkpoint
+//! and writes the best genome so far, which the game flies with
+//! `--pilot FILE`.
+
+use foxtroids::cli::{self, arg, fail};
+use foxtroids::neuro::{Fitness, Population, Trial};
+use std::path::PathBuf;
+use std::time::Instant;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let seed: u64 = arg(&args, "--seed").unwrap_or(1);
+    let checkpoint = arg(&args, "--checkpoint").unwrap_or(PathBuf::from("foxtroids.population"));
+    let best_path = arg(&args, "--best").unwrap_or(PathBuf::from("best.genome"));
+    let generations: u32 = arg(&args, "--generations").unwrap_or(100);
+    let threads = arg(&args, "--threads")
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |threads| threads.get()));
+    // The games: `--games N` seeds from `--seed`, each at most `--frames N`
+    // long, under the rules from `--settings FILE` and the rule options.
+    let rules = cli::rules(&args, cli::settings(&args, None).rules, &cli::RULE_OPTIONS);
+    let games: u64 = arg(&args, "--games").unwrap_or(3);
+    if games == 0 {
+        fail("--games must be at least 1");
+    }
+    let end = seed
+        .checked_add(games)
+        .unwrap_or_else(|| fail("--seed plus --games is too large"));
+    let trial = Trial {
+        rules,
+        seeds: (seed..end).collect(),
+        frames: arg(&args, "--frames").unwrap_or(60 * 60),
+    };
+    // `--resume` carries on from the checkpoint; otherwise a new population
+    // of `--population N` networks with `--hidden N` neurons, seeing the
+    // `--nearest N` asteroids and `--rays N` sensor rays.
+    let mut population = if args.iter().any(|arg| arg == "--resume") {
+        Population::load(&checkpoint).unwrap_or_else(|error| {
+            fail(format!(
+                "could not read {}: {}",
+                checkpoint.display(),
+                error
+            ))
+        })
+    } else {
+        let size = arg(&args, "--population").unwrap_or(64usize).max(2);
+        let hidden = arg(&args, "--hidden").unwrap_or(12usize);
+        let nearest = arg(&args, "--nearest").unwrap_or(4);
+        let rays = arg(&args, "--rays").unwrap_or(8);
+        Population::new(size, seed, nearest, rays, &[hidden])
+    };
+    if population.genomes.is_empty() {
+        fail(format!(
+            "{} holds no genomes to evolve",
+            checkpoint.display()
+        ));
+    }
+    println!(
+        "{} genomes, {} games of {} frames each, on {} threads",
+        population.genomes.len(),
+        games,
+        trial.frames,
+        threads
+    );
+    for _ in 0..generations {
+        let started = Instant::now();
+        let ranked = trial.rank(&population.genomes, threads);
+        let values: Vec<f64> = ranked.iter().map(Fitness::value).collect();
+        let (top, fitness) = values
+            .iter()
+            .zip(&ranked)
+            .max_by(|a, b| a.0.total_cmp(b.0))
+            .unwrap();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        println!(
+            "generation {} best {:.1} mean {:.1}: {:.0} points and {:.1} s alive a game, {:.0}% of shots hit ({:.1} s)",
+            population.generation,
+            top,
+            mean,
+            fitness.points as f64 / games as f64,
+            fitness.frames as f64 / 60.0 / games as f64,
+            fitness.accuracy() * 100.0,
+            started.elapsed().as_secs_f64()
+        );
+        let improved = population.best.as_ref().is_none_or(|best| *top > best.0);
+        population.breed(&values);
+        if let Err(error) = population.save(&checkpoint) {
+            fail(format!(
+                "could not save {}: {}",
+                checkpoint.display(),
+                error
+            ));
+        }
+        if let (true, Some((_, best))) = (improved, &population.best) {
+            if let Err(error) = best.save(&best_path) {
+                fail(format!("could not save {}: {}", best_path.display(), error));
+            }
+        }
+    }
+}
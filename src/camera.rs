@@ -0,0 +1,150 @@
+//! The view onto a world bigger than the screen: it follows the ship around
+//! the torus and zooms out when the ship is fast or asteroids close in.
+
+use crate::math::Vec2;
+use crate::render::{Renderer, Transform};
+use crate::shape::Shape;
+
+/// Furthest the camera zooms out; at 0.5 it shows four screens of world.
+pub const MIN_ZOOM: f64 = 0.5;
+// Share of the way to its target the camera moves each frame.
+const FOLLOW: f64 = 0.08;
+// How many frames of ship motion the camera looks ahead.
+const LEAD: f64 = 30.0;
+// Share of the way to the target zoom covered each frame.
+const ZOOM_RATE: f64 = 0.03;
+// Zoom lost at full speed, before threats are counted.
+const SPEED_ZOOM: f64 = 0.25;
+
+/// What part of the world is on screen. `view` is the screen's logical size;
+/// at `zoom` 1 a world unit is a screen unit.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    /// World position at the middle of the screen, inside the bounds.
+    pub center: Vec2,
+    pub zoom: f64,
+    pub view: Vec2,
+    /// Whether the camera moves at all; a world that fits on screen is
+    /// shown whole and still.
+    pub follows: bool,
+}
+impl Camera {
+    /// A camera for a `bounds` sized world on a `screen` sized view.
+    pub fn new(bounds: Vec2, screen: Vec2) -> Camera {
+        let follows = bounds.x > screen.x || bounds.y > screen.y;
+        Camera {
+            center: bounds / 2.0,
+            zoom: 1.0,
+            view: if follows { screen } else { bounds },
+            follows,
+        }
+    }
+    /// Size of the visible area in world units.
+    pub fn extent(&self) -> Vec2 {
+        self.view / self.zoom
+    }
+    /// World position at the top left of the screen. It can lie outside the
+    /// bounds; shapes are then drawn through their wrap copies.
+    pub fn min(&self) -> Vec2 {
+        self.center - self.extent() / 2.0
+    }
+    /// Maps world positions to the screen for drawing.
+    pub fn transform<'a>(&self, r: &'a mut dyn Renderer) -> Transform<'a> {
+        Transform {
+            inner: r,
+            scale: self.zoom,
+            offset: -self.min() * self.zoom,
+        }
+    }
+    /// Moves a frame closer to showing `ship` with room ahead of it, zoomed
+    /// out for its speed and for the nearest of `threats`.
+    pub fn follow<'a>(
+        &mut self,
+        bounds: Vec2,
+        ship: &Shape,
+        threats: impl Iterator<Item = &'a Shape>,
+    ) {
+        if !self.follows {
+            return;
+        }
+        let target = ship.pos + ship.velocity() * LEAD;
+        self.center =
+            (self.center + self.center.wrapped_delta(target, bounds) * FOLLOW).wrap(bounds);
+
+        let mut zoom = 1.0 - ship.s.abs().min(1.0) * SPEED_ZOOM;
+        // Anything within reach of the widest view pulls it out far enough to
+        // keep the threat on screen with its own size to spare.
+        let half = self.view.x.min(self.view.y) / 2.0;
+        let nearest = threats
+            .map(|threat| ship.pos.wrapped_distance(threat.pos, bounds) + threat.scale * 2.0)
+            .fold(f64::INFINITY, f64::min);
+        if nearest < half / MIN_ZOOM {
+            zoom = zoom.min(half / nearest);
+        }
+        let zoom = zoom.clamp(self.min_zoom(bounds), 1.0);
+        self.zoom += (zoom - self.zoom) * ZOOM_RATE;
+    }
+    // Never so far out that one copy of the world fills more than the screen.
+    fn min_zoom(&self, bounds: Vec2) -> f64 {
+        MIN_ZOOM
+            .max(self.view.x / bounds.x)
+            .max(self.view.y / bounds.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Color;
+    use crate::shape::ShapeType;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn ship(pos: Vec2) -> Shape {
+        let mut ship = Shape::new(
+            pos,
+            0.0,
+            10.0,
+            Color::WHITE,
+            ShapeType::Ship,
+            &mut StdRng::seed_from_u64(1),
+        );
+        ship.set_bound();
+        ship
+    }
+
+    #[test]
+    fn small_worlds_stay_still() {
+        let bounds = Vec2::new(600.0, 600.0);
+        let mut camera = Camera::new(bounds, Vec2::new(600.0, 600.0));
+        camera.follow(bounds, &ship(Vec2::new(10.0, 10.0)), std::iter::empty());
+        assert_eq!(camera.min(), Vec2::ZERO);
+        assert_eq!(camera.zoom, 1.0);
+    }
+
+    #[test]
+    fn follows_across_the_wrap() {
+        let bounds = Vec2::new(1800.0, 1800.0);
+        let mut camera = Camera::new(bounds, Vec2::new(600.0, 600.0));
+        camera.center = Vec2::new(1790.0, 900.0);
+        let ship = ship(Vec2::new(20.0, 900.0));
+        for _ in 0..200 {
+            camera.follow(bounds, &ship, std::iter::empty());
+        }
+        // It went the short way, over the edge, rather than back across.
+        assert!(camera.center.wrapped_distance(ship.pos, bounds) < 1.0);
+        assert_eq!(camera.zoom, 1.0);
+    }
+
+    #[test]
+    fn threats_zoom_out() {
+        let bounds = Vec2::new(1800.0, 1800.0);
+        let mut camera = Camera::new(bounds, Vec2::new(600.0, 600.0));
+        let rock = ship(Vec2::new(900.0, 1300.0));
+        let player = ship(Vec2::new(900.0, 900.0));
+        for _ in 0..300 {
+            camera.follow(bounds, &player, std::iter::once(&rock));
+        }
+        assert!(camera.zoom < 0.75 && camera.zoom >= MIN_ZOOM);
+    }
+}
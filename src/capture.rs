@@ -0,0 +1,113 @@
+//! Saving rendered frames: single screenshots and numbered frame sequences.
+
+use crate::render::software::Framebuffer;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A file name like `foxtroids-20240131-235959.123.png` for the current UTC time.
+pub fn screenshot_name() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let (secs, millis) = (now.as_secs(), now.subsec_millis());
+    let (year, month, day) = civil_date(secs / 86400);
+    let time = secs % 86400;
+    format!(
+        "foxtroids-{:04}{:02}{:02}-{:02}{:02}{:02}.{:03}.png",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        millis
+    )
+}
+// Year, month and day of a day count since 1970-01-01, from Howard Hinnant's
+// `civil_from_days`.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Writes every `every`th frame it is offered to `dir` as `frame_000000.png`,
+/// `frame_000001.png` and so on, numbered by saved frame.
+pub struct FrameRecorder {
+    dir: PathBuf,
+    every: u64,
+    offered: u64,
+    saved: u64,
+}
+impl FrameRecorder {
+    /// Creates `dir` if needed.
+    pub fn new(dir: impl AsRef<Path>, every: u64) -> io::Result<FrameRecorder> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(FrameRecorder {
+            dir: dir.as_ref().to_path_buf(),
+            every: every.max(1),
+            offered: 0,
+            saved: 0,
+        })
+    }
+    /// Whether the next frame will be kept, so callers can skip reading it back.
+    pub fn wants_next(&self) -> bool {
+        self.offered.is_multiple_of(self.every)
+    }
+    /// Offers the next frame. `frame` is only called when it will be saved.
+    pub fn offer(&mut self, frame: impl FnOnce() -> Framebuffer) -> io::Result<()> {
+        let keep = self.wants_next();
+        self.offered += 1;
+        if keep {
+            let path = self.dir.join(format!("frame_{:06}.png", self.saved));
+            frame().save_png(path)?;
+            self.saved += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(11016), (2000, 2, 29));
+        assert_eq!(civil_date(19753), (2024, 1, 31));
+        assert!(screenshot_name().starts_with("foxtroids-20"));
+    }
+
+    #[test]
+    fn records_every_nth_frame() {
+        let dir = std::env::temp_dir().join(format!("foxtroids-frames-{}", std::process::id()));
+        let mut recorder = FrameRecorder::new(&dir, 3).unwrap();
+        let mut rendered = 0;
+        for _ in 0..7 {
+            recorder
+                .offer(|| {
+                    rendered += 1;
+                    Framebuffer::new(2, 2)
+                })
+                .unwrap();
+        }
+        let mut names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(rendered, 3);
+        assert_eq!(
+            names,
+            ["frame_000000.png", "frame_000001.png", "frame_000002.png"]
+        );
+    }
+}
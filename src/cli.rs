@@ -0,0 +1,46 @@
+//! Command line options the game and the tool binaries share.
+
+use crate::settings::{Rules, Settings};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Options that override the rule of the same name: `--arena N`,
+/// `--players N`, `--mode coop|versus` and `--lives N`.
+pub const RULE_OPTIONS: [&str; 4] = ["arena", "players", "mode", "lives"];
+
+/// The value after `name` on the command line, if `name` is there. Exits
+/// with a message if the value is missing or does not parse.
+pub fn arg<T: FromStr>(args: &[String], name: &str) -> Option<T> {
+    let i = args.iter().position(|arg| arg == name)?;
+    let value = (args.get(i + 1)).unwrap_or_else(|| fail(format!("{} needs a value", name)));
+    let parsed = value.parse();
+    Some(parsed.unwrap_or_else(|_| fail(format!("bad {} value: {}", name, value))))
+}
+
+/// Prints `message` and exits unsuccessfully.
+pub fn fail(message: impl Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+/// The settings in `--settings FILE`, else in `default` if given and
+/// present, else the defaults.
+pub fn settings(args: &[String], default: Option<&str>) -> Settings {
+    match arg::<PathBuf>(args, "--settings").or(default.map(PathBuf::from)) {
+        Some(path) => Settings::load(&path)
+            .unwrap_or_else(|error| fail(format!("could not read {}: {}", path.display(), error))),
+        None => Settings::default(),
+    }
+}
+
+/// `rules` with the given `options`, usually `RULE_OPTIONS`, set from the
+/// command line.
+pub fn rules(args: &[String], mut rules: Rules, options: &[&str]) -> Rules {
+    for key in options {
+        if let Some(value) = arg::<String>(args, &format!("--{}", key)) {
+            rules.set(key, &value).unwrap_or_else(|error| fail(error));
+        }
+    }
+    rules
+}
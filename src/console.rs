@@ -0,0 +1,490 @@
+//! In-game developer console, only built with the `dev` feature.
+
+use crate::font::draw_text;
+use crate::math::Vec2;
+use crate::render::{Color, Renderer};
+use crate::shape::{ShapeType, MAX_ASTEROID_SCALE};
+use crate::world::World;
+use std::collections::VecDeque;
+
+const SCROLLBACK: usize = 16;
+// Most asteroids one command sends, so a typo cannot stall the game.
+const MAX_SPAWN: usize = 200;
+const COMMANDS: [&str; 12] = [
+    "autopilot",
+    "clear",
+    "god",
+    "help",
+    "history",
+    "revive",
+    "seed",
+    "set",
+    "ship",
+    "spawn",
+    "timescale",
+    "wave",
+];
+const VARIABLES: [&str; 3] = ["fire_delay", "scale", "score"];
+const HELP: [&str; 11] = [
+    "SPAWN ASTEROID [COUNT] [SIZE=N]",
+    "WAVE N        CLEAR AND SEND N ASTEROIDS",
+    "GOD ON|OFF    IGNORE ASTEROID HITS",
+    "AUTOPILOT ON|OFF LET THE AI FLY",
+    "SET FIRE_DELAY|SCALE|SCORE N",
+    "SEED N        RESTART WITH SEED N",
+    "TIMESCALE X   SIMULATION SPEED",
+    "SHIP ASTEROID|NORMAL",
+    "CLEAR         REMOVE ALL ASTEROIDS",
+    "REVIVE        BRING THE SHIP BACK",
+    "HISTORY       LIST PAST COMMANDS",
+];
+
+/// Keys the console reacts to; the frontend maps its own key events onto these.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ConsoleKey {
+    /// Backtick: opens and closes the console.
+    Toggle,
+    Enter,
+    Backspace,
+    Tab,
+    Up,
+    Down,
+    Escape,
+}
+
+/// Drop-down developer console, opened with the backtick key. Lines typed into
+/// it are run against the world; the game is paused while it is open.
+pub struct Console {
+    pub open: bool,
+    /// Simulation steps per rendered frame; fractions skip frames.
+    pub timescale: f64,
+    /// The built-in pilot flies the first ship.
+    pub autopilot: bool,
+    input: String,
+    history: Vec<String>,
+    // Position while browsing the history with the arrow keys.
+    browsing: Option<usize>,
+    output: VecDeque<String>,
+}
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            timescale: 1.0,
+            autopilot: false,
+            input: String::new(),
+            history: Vec::new(),
+            browsing: None,
+            output: VecDeque::new(),
+        }
+    }
+    fn print(&mut self, line: impl Into<String>) {
+        if self.output.len() == SCROLLBACK {
+            self.output.pop_front();
+        }
+        self.output.push_back(line.into());
+    }
+    /// Handles a key press. Returns true when the console used it and it
+    /// should not reach the game.
+    pub fn key(&mut self, key: Option<ConsoleKey>, world: &mut World) -> bool {
+        if key == Some(ConsoleKey::Toggle) {
+            self.open = !self.open;
+            return true;
+        }
+        if !self.open {
+            return false;
+        }
+        match key {
+            Some(ConsoleKey::Enter) => {
+                let line = std::mem::take(&mut self.input);
+                self.submit(&line, world);
+            }
+            Some(ConsoleKey::Backspace) => {
+                self.input.pop();
+            }
+            Some(ConsoleKey::Tab) => self.complete(),
+            Some(ConsoleKey::Up) => self.browse(-1),
+            Some(ConsoleKey::Down) => self.browse(1),
+            Some(ConsoleKey::Escape) => self.open = false,
+            Some(ConsoleKey::Toggle) | None => {}
+        }
+        true
+    }
+    /// Typed text; ignored while closed.
+    pub fn text(&mut self, text: &str) -> bool {
+        if self.open {
+            self.input.extend(text.chars().filter(|c| *c != '`'));
+        }
+        self.open
+    }
+    fn browse(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() as isize - 1;
+        let pos = match self.browsing {
+            None if step < 0 => last,
+            None => return,
+            Some(pos) => pos as isize + step,
+        };
+        if pos > last {
+            self.browsing = None;
+            self.input.clear();
+        } else {
+            let pos = pos.max(0) as usize;
+            self.browsing = Some(pos);
+            self.input = self.history[pos].clone();
+        }
+    }
+    /// Runs one line, echoing it and its result into the scrollback.
+    pub fn submit(&mut self, line: &str, world: &mut World) {
+        let line = line.trim();
+        self.browsing = None;
+        if line.is_empty() {
+            return;
+        }
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.print(format!("> {}", line));
+        match self.execute(line, world) {
+            Ok(lines) => {
+                for line in lines {
+                    self.print(line);
+                }
+            }
+            Err(error) => self.print(format!("ERROR: {}", error)),
+        }
+    }
+    pub fn execute(&mut self, line: &str, world: &mut World) -> Result<Vec<String>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |i: usize| -> Result<f64, String> {
+            let word = words.get(i).ok_or("missing number")?;
+            word.parse().map_err(|_| format!("not a number: {}", word))
+        };
+        match words.first().copied().unwrap_or("") {
+            "help" => Ok(HELP.iter().map(|line| line.to_string()).collect()),
+            "history" => Ok(self.history.clone()),
+            "spawn" => {
+                if words.get(1) != Some(&"asteroid") {
+                    return Err("usage: spawn asteroid [count] [size=N]".into());
+                }
+                let mut count = 1;
+                let mut size = None;
+                for word in &words[2..] {
+                    if let Some(value) = word.strip_prefix("size=") {
+                        size = Some(
+                            value
+                                .parse::<f64>()
+                                .ok()
+                                .filter(|size| *size > 0.0 && *size <= MAX_ASTEROID_SCALE)
+                                .ok_or(format!(
+                                    "size must be above 0 and at most {}",
+                                    MAX_ASTEROID_SCALE
+                                ))?,
+                        );
+                    } else {
+                        count = word.parse().map_err(|_| format!("bad count: {}", word))?;
+                    }
+                }
+                let count = count.min(MAX_SPAWN);
+                for _ in 0..count {
+                    let handle = world.spawn_asteroid();
+                    if let (Some(size), Some(asteroid)) = (size, world.asteroids.get_mut(handle)) {
+                        asteroid.scale = size;
+                    }
+                }
+                Ok(vec![format!("spawned {}", count)])
+            }
+            "wave" => {
+                let count = (number(1)? as usize).min(MAX_SPAWN);
+                world.asteroids.clear();
+                for _ in 0..count {
+                    world.spawn_asteroid();
+                }
+                Ok(vec![format!("wave of {}", count)])
+            }
+            "god" => {
+                world.god = match words.get(1).copied() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    None => !world.god,
+                    Some(other) => return Err(format!("god on|off, not {}", other)),
+                };
+                Ok(vec![format!(
+                    "god {}",
+                    if world.god { "on" } else { "off" }
+                )])
+            }
+            "autopilot" => {
+                self.autopilot = match words.get(1).copied() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    None => !self.autopilot,
+                    Some(other) => return Err(format!("autopilot on|off, not {}", other)),
+                };
+                Ok(vec![format!(
+                    "autopilot {}",
+                    if self.autopilot { "on" } else { "off" }
+                )])
+            }
+            "set" => {
+                let name = words.get(1).copied().unwrap_or("");
+                let value = number(2)?;
+                match name {
+                    "fire_delay" if (1.0..=u8::MAX as f64).contains(&value) => {
+                        world.fire_period = value as u8;
+                        // A shorter period must not leave ships waiting longer.
+                        for ship in world.ships.iter_mut() {
+                            ship.fire_delay = 0;
+                        }
+                    }
+                    "fire_delay" => {
+                        return Err(format!("fire_delay must be between 1 and {}", u8::MAX))
+                    }
+                    "scale" if value.is_finite() && value > 0.0 => {
+                        world.ships[0].shape.scale = value
+                    }
+                    "scale" => return Err("scale must be above 0".into()),
+                    "score" => world.ships[0].score = value as u32,
+                    _ => return Err(format!("unknown variable: {}", name)),
+                }
+                Ok(vec![format!("{} = {}", name, value)])
+            }
+            "seed" => {
+                let seed = words
+                    .get(1)
+                    .and_then(|word| word.parse().ok())
+                    .ok_or("usage: seed N")?;
+                *world = World::with_rules(world.rules.clone(), seed);
+                Ok(vec![format!("restarted with seed {}", seed)])
+            }
+            "timescale" => {
+                let scale = number(1)?;
+                if !(0.0..=8.0).contains(&scale) {
+                    return Err("timescale must be between 0 and 8".into());
+                }
+                self.timescale = scale;
+                Ok(vec![format!("timescale {}", scale)])
+            }
+            "ship" => {
+                let kind = match words.get(1).copied() {
+                    Some("asteroid") => ShapeType::Asteroid(0.0),
+                    Some("normal") => ShapeType::Ship,
+                    _ => return Err("usage: ship asteroid|normal".into()),
+                };
+                world.ships[0].shape.set_kind(kind, &mut world.rng);
+                Ok(Vec::new())
+            }
+            "clear" => {
+                world.asteroids.clear();
+                Ok(Vec::new())
+            }
+            "revive" => {
+                for ship in world.ships.iter_mut() {
+                    ship.alive = true;
+                }
+                Ok(Vec::new())
+            }
+            other => Err(format!("unknown command: {}", other)),
+        }
+    }
+    fn candidates(&self, words: &[&str]) -> Vec<&'static str> {
+        match words {
+            [] | [_] => COMMANDS.to_vec(),
+            ["spawn", _] => vec!["asteroid"],
+            ["god" | "autopilot", _] => vec!["on", "off"],
+            ["set", _] => VARIABLES.to_vec(),
+            ["ship", _] => vec!["asteroid", "normal"],
+            _ => Vec::new(),
+        }
+    }
+    /// Completes the word under the cursor, or lists the options when more
+    /// than one fits.
+    pub fn complete(&mut self) {
+        let input = self.input.clone();
+        let mut words: Vec<&str> = input.split_whitespace().collect();
+        if input.is_empty() || input.ends_with(' ') {
+            words.push("");
+        }
+        let partial = words.last().copied().unwrap_or("");
+        let matches: Vec<&str> = self
+            .candidates(&words)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(partial))
+            .collect();
+        let completed = match matches.as_slice() {
+            [] => return,
+            [only] => format!("{} ", only),
+            _ => {
+                let listing = matches.join(" ");
+                let mut prefix = matches[0].to_string();
+                for other in &matches[1..] {
+                    while !other.starts_with(&prefix) {
+                        prefix.pop();
+                    }
+                }
+                self.print(listing);
+                prefix
+            }
+        };
+        words.pop();
+        words.push(&completed);
+        self.input = words.join(" ");
+    }
+    pub fn draw(&self, screen: Vec2, r: &mut dyn Renderer) {
+        if !self.open {
+            return;
+        }
+        let height = 14.0 * (SCROLLBACK as f64 + 1.0) + 12.0;
+        r.set_color(Color::rgba(0, 0, 40, 220));
+        r.fill_rect(Vec2::ZERO, Vec2::new(screen.x, height));
+        r.set_color(Color::rgb(120, 120, 200));
+        r.line(Vec2::new(0.0, height), Vec2::new(screen.x, height));
+        for (i, line) in self.output.iter().enumerate() {
+            draw_text(
+                line,
+                Vec2::new(8.0, 6.0 + i as f64 * 14.0),
+                8.0,
+                Color::rgb(200, 200, 200),
+                r,
+            );
+        }
+        draw_text(
+            &format!("> {}_", self.input),
+            Vec2::new(8.0, 6.0 + SCROLLBACK as f64 * 14.0),
+            8.0,
+            Color::WHITE,
+            r,
+        );
+    }
+}
+// The single-key shortcuts that predate the console.
+pub fn debug_key(key: char, world: &mut World) {
+    match key {
+        'r' => world.ships[0]
+            .shape
+            .set_kind(ShapeType::Asteroid(0.0), &mut world.rng),
+        't' => world.ships[0]
+            .shape
+            .set_kind(ShapeType::Ship, &mut world.rng),
+        'n' => {
+            world.spawn_asteroid();
+        }
+        'c' => world.asteroids.clear(),
+        '.' => world.ships[0].shape.scale += 1.0,
+        ',' => world.ships[0].shape.scale -= 1.0,
+        '=' => world.ships[0].alive = true,
+        _ => {}
+    }
+}
+impl Default for Console {
+    fn default() -> Self {
+        Console::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        World::new(Vec2::new(600.0, 600.0), 1)
+    }
+
+    #[test]
+    fn spawn_with_count_and_size() {
+        let mut console = Console::new();
+        let mut world = world();
+        console
+            .execute("spawn asteroid 5 size=60", &mut world)
+            .unwrap();
+        assert_eq!(world.asteroids.len(), 5);
+        assert!(world.asteroids.values().all(|a| a.scale == 60.0));
+        assert!(console.execute("spawn saucer", &mut world).is_err());
+        for size in ["0", "-5", "NaN", "81"] {
+            let line = format!("spawn asteroid size={}", size);
+            assert!(console.execute(&line, &mut world).is_err());
+        }
+        console
+            .execute("spawn asteroid 1000000000", &mut world)
+            .unwrap();
+        assert_eq!(world.asteroids.len(), 5 + MAX_SPAWN);
+    }
+
+    #[test]
+    fn settings_commands() {
+        let mut console = Console::new();
+        let mut world = world();
+        console.execute("god on", &mut world).unwrap();
+        assert!(world.god);
+        console.execute("set fire_delay 5", &mut world).unwrap();
+        assert_eq!(world.fire_period, 5);
+        assert!(console.execute("set fire_delay 0", &mut world).is_err());
+        assert!(console.execute("set fire_delay 1e9", &mut world).is_err());
+        assert_eq!(world.fire_period, 5);
+        world.ships[0].fire_delay = 3;
+        console.execute("set scale 2", &mut world).unwrap();
+        assert_eq!(
+            (world.ships[0].shape.scale, world.ships[0].fire_delay),
+            (2.0, 3)
+        );
+        for bad in ["0", "-1", "inf", "NaN"] {
+            assert!(console
+                .execute(&format!("set scale {}", bad), &mut world)
+                .is_err());
+        }
+        assert_eq!(world.ships[0].shape.scale, 2.0);
+        console.execute("timescale 0.5", &mut world).unwrap();
+        assert_eq!(console.timescale, 0.5);
+        console.execute("autopilot", &mut world).unwrap();
+        assert!(console.autopilot);
+        console.execute("wave 7", &mut world).unwrap();
+        assert_eq!(world.asteroids.len(), 7);
+        console.execute("seed 1234", &mut world).unwrap();
+        assert_eq!(world.seed, 1234);
+        assert!(world.asteroids.is_empty());
+    }
+
+    #[test]
+    fn history_and_completion() {
+        let mut console = Console::new();
+        let mut world = world();
+        console.submit("god on", &mut world);
+        console.submit("wave 2", &mut world);
+        console.browse(-1);
+        assert_eq!(console.input, "wave 2");
+        console.browse(-1);
+        assert_eq!(console.input, "god on");
+        console.browse(1);
+        console.browse(1);
+        assert_eq!(console.input, "");
+
+        console.input = "sp".into();
+        console.complete();
+        assert_eq!(console.input, "spawn ");
+        console.complete();
+        assert_eq!(console.input, "spawn asteroid ");
+        console.input = "s".into();
+        console.complete();
+        assert_eq!(console.input, "s");
+        console.input = "set f".into();
+        console.complete();
+        assert_eq!(console.input, "set fire_delay ");
+    }
+
+    #[test]
+    fn keys_only_reach_an_open_console() {
+        let mut console = Console::new();
+        let mut world = world();
+        assert!(!console.text("god"));
+        assert!(!console.key(Some(ConsoleKey::Enter), &mut world));
+        assert!(console.key(Some(ConsoleKey::Toggle), &mut world));
+        assert!(console.text("god`"));
+        assert!(console.key(Some(ConsoleKey::Enter), &mut world));
+        assert!(world.god);
+        assert!(console.key(None, &mut world));
+        console.key(Some(ConsoleKey::Escape), &mut world);
+        assert!(!console.open);
+    }
+}
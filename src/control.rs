@@ -0,0 +1,123 @@
+//! Whatever flies a ship: a player at the keyboard or a gamepad, a recording
+//! or a bot. Every tick a controller looks at the world and answers with the
+//! buttons its ship holds, so any of them can fly any ship.
+
+use crate::input::ShipInput;
+use crate::world::{Ship, World};
+use std::ops::DerefMut;
+
+/// What a controller sees before a tick: the whole world, and which of its
+/// ships is the one it flies.
+#[derive(Copy, Clone)]
+pub struct Observation<'a> {
+    pub world: &'a World,
+    pub ship: usize,
+}
+impl Observation<'_> {
+    pub fn own(&self) -> &Ship {
+        &self.world.ships[self.ship]
+    }
+}
+
+/// Turns observations into commands: turn, thrust, fire and hyperspace.
+pub trait ShipController {
+    /// The command for the tick about to be simulated.
+    fn command(&mut self, observation: &Observation) -> ShipInput;
+    /// Called once the game it flew in is over, before any next game.
+    fn finish(&mut self, _observation: &Observation) {}
+}
+/// A closure can fly a ship, which suits short scripted pilots.
+impl<F: FnMut(&Observation) -> ShipInput> ShipController for F {
+    fn command(&mut self, observation: &Observation) -> ShipInput {
+        self(observation)
+    }
+}
+
+/// Leaves the ship alone.
+pub struct Idle;
+impl ShipController for Idle {
+    fn command(&mut self, _: &Observation) -> ShipInput {
+        ShipInput::default()
+    }
+}
+
+/// Several controllers flying one ship together, such as a keyboard and a
+/// gamepad; the buttons held on any of them count. They may be boxed or
+/// borrowed from controllers the frontend keeps, as `&mut dyn ShipController`.
+pub struct Merged<C>(pub Vec<C>);
+impl<C> Default for Merged<C> {
+    fn default() -> Self {
+        Merged(Vec::new())
+    }
+}
+impl<C, T> ShipController for Merged<C>
+where
+    C: DerefMut<Target = T>,
+    T: ShipController + ?Sized,
+{
+    fn command(&mut self, observation: &Observation) -> ShipInput {
+        self.0
+            .iter_mut()
+            .fold(ShipInput::default(), |input, controller| {
+                input | controller.command(observation)
+            })
+    }
+    fn finish(&mut self, observation: &Observation) {
+        for controller in &mut self.0 {
+            controller.finish(observation);
+        }
+    }
+}
+
+/// The commands of `controllers` for the next tick of `world`, the first
+/// flying ship 0 and so on. Ships without a controller are left alone.
+pub fn commands(world: &World, controllers: &mut [Box<dyn ShipController>]) -> Vec<ShipInput> {
+    (0..world.ships.len())
+        .map(|ship| match controllers.get_mut(ship) {
+            Some(controller) => controller.command(&Observation { world, ship }),
+            None => ShipInput::default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Rules;
+
+    #[test]
+    fn controllers_fly_their_own_ships() {
+        let rules = Rules {
+            players: 3,
+            ..Rules::default()
+        };
+        let mut world = World::with_rules(rules, 1);
+        // Player 1 thrusts with two controllers, one of them only while the
+        // ship is still near its start.
+        let start = world.ships[1].shape.pos;
+        let thrust = |_: &Observation| ShipInput {
+            thrust: true,
+            ..ShipInput::default()
+        };
+        let near_start = move |observation: &Observation| ShipInput {
+            thrust: observation.own().shape.pos == start,
+            ..ShipInput::default()
+        };
+        let mut controllers: Vec<Box<dyn ShipController>> = vec![
+            Box::new(Idle),
+            Box::new(Merged::<Box<dyn ShipController>>(vec![
+                Box::new(thrust),
+                Box::new(near_start),
+            ])),
+        ];
+        let inputs = commands(&world, &mut controllers);
+        assert_eq!(inputs.len(), 3);
+        assert!(inputs[1].thrust && !inputs[0].thrust && !inputs[2].thrust);
+        for _ in 0..30 {
+            let inputs = commands(&world, &mut controllers);
+            world.step(&inputs);
+        }
+        assert_ne!(world.ships[1].shape.pos, start);
+        assert_eq!(world.ships[0].shape.pos, world.ships[0].spawn);
+    }
+}
@@ -0,0 +1,167 @@
+//! Debug overlay drawn on top of the game.
+
+use crate::font::draw_text;
+use crate::math::Vec2;
+use crate::render::{Color, Renderer};
+use crate::shape::Shape;
+use crate::world::World;
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+const HISTORY: usize = 120;
+const GRID: Color = Color::rgb(40, 40, 40);
+const CELL: Color = Color::rgb(0, 90, 0);
+const CIRCLE: Color = Color::rgb(0, 255, 0);
+const POLYGON: Color = Color::rgb(0, 200, 255);
+const HEADING: Color = Color::rgb(255, 60, 60);
+const VELOCITY: Color = Color::rgb(255, 255, 0);
+const DUMMY: Color = Color::rgb(255, 0, 255);
+const TEXT: Color = Color::rgb(200, 200, 200);
+
+/// In-window debug view, toggled with F3: collision shapes, motion vectors,
+/// wrap copies and the broad-phase grid, plus frame timing and world stats.
+pub struct DebugOverlay {
+    pub visible: bool,
+    // Time spent updating and drawing each frame, in milliseconds.
+    frame_times: VecDeque<f64>,
+    // Time between the starts of consecutive frames, in milliseconds.
+    intervals: VecDeque<f64>,
+}
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            visible: false,
+            frame_times: VecDeque::with_capacity(HISTORY),
+            intervals: VecDeque::with_capacity(HISTORY),
+        }
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    pub fn record(&mut self, frame_time: Duration, interval: Duration) {
+        for (history, value) in [
+            (&mut self.frame_times, frame_time),
+            (&mut self.intervals, interval),
+        ] {
+            if history.len() == HISTORY {
+                history.pop_front();
+            }
+            history.push_back(value.as_secs_f64() * 1000.0);
+        }
+    }
+    pub fn fps(&self) -> f64 {
+        let total: f64 = self.intervals.iter().sum();
+        if total == 0.0 {
+            0.0
+        } else {
+            self.intervals.len() as f64 * 1000.0 / total
+        }
+    }
+    pub fn draw(&self, world: &World, r: &mut dyn Renderer) {
+        if !self.visible {
+            return;
+        }
+        let view = &mut world.camera.transform(r);
+        self.draw_grid(world, view);
+        for ship in world.ships.iter().filter(|ship| ship.alive) {
+            draw_shape(&ship.shape, world, view);
+        }
+        for asteroid in world.asteroids.values() {
+            draw_shape(asteroid, world, view);
+        }
+        for bullet in world.bullets.values() {
+            draw_shape(bullet, world, view);
+        }
+        self.draw_stats(world, r);
+        self.draw_graph(world.camera.view, r);
+    }
+    fn draw_grid(&self, world: &World, r: &mut dyn Renderer) {
+        let grid = &world.grid;
+        r.set_color(GRID);
+        for col in 1..grid.cols {
+            let x = col as f64 * grid.cell;
+            r.line(Vec2::new(x, 0.0), Vec2::new(x, world.bounds.y));
+        }
+        for row in 1..grid.rows {
+            let y = row as f64 * grid.cell;
+            r.line(Vec2::new(0.0, y), Vec2::new(world.bounds.x, y));
+        }
+        r.set_color(CELL);
+        for (col, row, _) in grid.occupied() {
+            let corner = Vec2::new(col as f64, row as f64) * grid.cell;
+            let size = grid.cell - 2.0;
+            r.polygon(&[
+                corner + Vec2::new(1.0, 1.0),
+                corner + Vec2::new(size, 1.0),
+                corner + Vec2::new(size, size),
+                corner + Vec2::new(1.0, size),
+            ]);
+        }
+    }
+    fn draw_stats(&self, world: &World, r: &mut dyn Renderer) {
+        let frame_time = self.frame_times.back().copied().unwrap_or(0.0);
+        let lines = [
+            format!("FPS {:.1}", self.fps()),
+            format!("FRAME {:.2}MS", frame_time),
+            format!("TICK {}", world.frame),
+            format!("ASTEROIDS {}", world.asteroids.len()),
+            format!("BULLETS {}", world.bullets.len()),
+            format!("SEED {}", world.seed),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, Vec2::new(10.0, 34.0 + i as f64 * 14.0), 8.0, TEXT, r);
+        }
+    }
+    // Frame times as bars along the bottom edge; the line marks 60 FPS.
+    fn draw_graph(&self, bounds: Vec2, r: &mut dyn Renderer) {
+        let base = bounds.y - 10.0;
+        let scale = 2.0;
+        let budget = base - 1000.0 / 60.0 * scale;
+        r.set_color(GRID);
+        r.line(
+            Vec2::new(10.0, budget),
+            Vec2::new(10.0 + HISTORY as f64 * 2.0, budget),
+        );
+        for (i, ms) in self.frame_times.iter().enumerate() {
+            r.set_color(if *ms > 1000.0 / 60.0 { HEADING } else { CIRCLE });
+            let x = 10.0 + i as f64 * 2.0;
+            r.line(
+                Vec2::new(x, base),
+                Vec2::new(x, base - (ms * scale).max(1.0)),
+            );
+        }
+    }
+}
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        DebugOverlay::new()
+    }
+}
+
+fn draw_circle(center: Vec2, radius: f64, color: Color, r: &mut dyn Renderer) {
+    let points: Vec<Vec2> = (0..24)
+        .map(|i| center + Vec2::from_angle(i as f64 * TAU / 24.0) * radius)
+        .collect();
+    r.set_color(color);
+    r.polygon(&points);
+}
+fn draw_shape(shape: &Shape, world: &World, r: &mut dyn Renderer) {
+    // Radius used against the ship, and the polygon bullets are tested against.
+    draw_circle(shape.pos, shape.scale * 0.8, CIRCLE, r);
+    r.set_color(POLYGON);
+    r.polygon(&shape.verticies(Vec2::ZERO));
+    r.set_color(DUMMY);
+    for shift in shape.copies(world.bounds, &world.camera) {
+        if shift == Vec2::ZERO {
+            continue;
+        }
+        r.polygon(&shape.verticies(shift));
+    }
+    let heading = shape.pos + Vec2::from_angle(shape.rot) * shape.scale * 1.5;
+    r.set_color(HEADING);
+    r.line(shape.pos, heading);
+    // Velocity is drawn ten frames long so slow drifts are still visible.
+    r.set_color(VELOCITY);
+    r.line(shape.pos, shape.pos + shape.velocity() * 10.0);
+}
@@ -0,0 +1,262 @@
+//! The game as an environment for learning agents. An episode starts from a
+//! seed; each step holds one set of buttons for a few frames and answers with
+//! what the ship sees, the points it scored and whether its game is over.
+//! The `foxtroids-env` binary serves this over stdin and stdout, and
+//! `agent::Agent` lets a program speaking the same protocol fly any ship.
+
+pub mod agent;
+pub mod protocol;
+
+use crate::ai::Pilot;
+use crate::control::{commands, Idle, Observation, ShipController};
+use crate::input::ShipInput;
+use crate::math::Vec2;
+use crate::render::software::Framebuffer;
+use crate::render::{Color, Renderer, Viewport};
+use crate::sensors::{cast, Hit};
+use crate::settings::{Rules, Scaling};
+use crate::world::{World, PLAYFIELD};
+
+// Numbers describing the agent's own ship, ahead of the asteroids.
+const OWN: usize = 8;
+// Numbers describing each of the nearest asteroids.
+const PER_ASTEROID: usize = 5;
+// Numbers describing each sensor ray.
+const PER_RAY: usize = 4;
+// Speeds are given in units of this many pixels per frame.
+const SPEED_UNIT: f64 = 10.0;
+/// How far sensor rays reach: half a screen.
+pub const RAY_RANGE: f64 = PLAYFIELD.x / 2.0;
+
+/// How episodes are played and observed.
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub rules: Rules,
+    /// Frames each action is held for.
+    pub frame_skip: u32,
+    /// Asteroids in the feature vector, nearest first.
+    pub nearest: usize,
+    /// Sensor rays in the feature vector, after the asteroids.
+    pub rays: usize,
+    /// Width and height of the greyscale frame in observations, if any.
+    pub pixels: Option<(usize, usize)>,
+    /// Frames after which an episode is cut short; 0 plays to the end.
+    pub max_frames: u64,
+}
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            rules: Rules::default(),
+            frame_skip: 4,
+            nearest: 8,
+            rays: 0,
+            pixels: None,
+            max_frames: 0,
+        }
+    }
+}
+
+/// What the agent gets back from a reset or a step.
+pub struct Step {
+    pub features: Vec<f32>,
+    /// Row-major brightness, one byte per pixel.
+    pub pixels: Option<Vec<u8>>,
+    /// Points the agent's ship scored during the step.
+    pub reward: f64,
+    /// The agent's ship is out of lives, or the episode was cut short.
+    pub done: bool,
+    /// Cut short by `max_frames` rather than lost.
+    pub truncated: bool,
+}
+
+/// One game with the agent flying ship 0. In games for more players the
+/// built-in pilot flies the others.
+pub struct Env {
+    pub config: EnvConfig,
+    pub world: World,
+    pilots: Vec<Box<dyn ShipController>>,
+}
+impl Env {
+    pub fn new(config: EnvConfig) -> Env {
+        let world = World::with_rules(config.rules.clone(), 0);
+        let mut pilots: Vec<Box<dyn ShipController>> = vec![Box::new(Idle)];
+        for _ in 1..config.rules.players {
+            pilots.push(Box::new(Pilot));
+        }
+        Env {
+            config,
+            world,
+            pilots,
+        }
+    }
+    /// Length of the feature vector in every observation.
+    pub fn feature_count(&self) -> usize {
+        feature_count(self.config.nearest, self.config.rays)
+    }
+    /// Starts a new episode.
+    pub fn reset(&mut self, seed: u64) -> Step {
+        self.world = World::with_rules(self.config.rules.clone(), seed);
+        self.observe(0.0)
+    }
+    /// Holds `action` for `frame_skip` frames, or until the episode ends.
+    pub fn step(&mut self, action: ShipInput) -> Step {
+        let before = self.world.ships[0].score;
+        for _ in 0..self.config.frame_skip.max(1) {
+            if self.finished() || self.truncated() {
+                break;
+            }
+            let mut inputs = commands(&self.world, &mut self.pilots);
+            inputs[0] = action;
+            self.world.step(&inputs);
+        }
+        self.observe((self.world.ships[0].score - before) as f64)
+    }
+    fn finished(&self) -> bool {
+        let ship = &self.world.ships[0];
+        !ship.alive && ship.respawn == 0
+    }
+    fn truncated(&self) -> bool {
+        self.config.max_frames > 0 && self.world.frame >= self.config.max_frames
+    }
+    fn observe(&self, reward: f64) -> Step {
+        let observation = Observation {
+            world: &self.world,
+            ship: 0,
+        };
+        let truncated = !self.finished() && self.truncated();
+        Step {
+            features: features(&observation, self.config.nearest, self.config.rays),
+            pixels: self
+                .config
+                .pixels
+                .map(|(width, height)| pixels(&self.world, width, height)),
+            reward,
+            done: self.finished() || truncated,
+            truncated,
+        }
+    }
+}
+
+/// Length of the vector `features` returns.
+pub fn feature_count(nearest: usize, rays: usize) -> usize {
+    OWN + PER_ASTEROID * nearest + PER_RAY * rays
+}
+
+/// The ship and the `nearest` asteroids around it as numbers of about unit
+/// size. The ship comes first: alive, forward speed, turn speed, the sine
+/// and cosine of its heading, gun loaded, hyperspace charged and shielded.
+/// Each asteroid follows, nearest first, as its position and velocity
+/// relative to the ship (x ahead, y to the right, positions in playfield
+/// widths) and its radius; missing asteroids are all zeros. Last come the
+/// `rays` sensor rays from `sensors::cast`, each as the share of
+/// `RAY_RANGE` it got, the size tier of the asteroid it hit counting from 1
+/// in thirds, and whether it hit a ship and a bullet.
+pub fn features(observation: &Observation, nearest: usize, rays: usize) -> Vec<f32> {
+    let world = observation.world;
+    let ship = observation.own();
+    let me = &ship.shape;
+    let flag = |on: bool| on as u8 as f64;
+    let mut features = vec![
+        flag(ship.alive),
+        me.s,
+        me.s_rot,
+        me.rot.sin(),
+        me.rot.cos(),
+        flag(ship.fire_delay == 0),
+        flag(ship.hyperspace == 0),
+        flag(ship.shield > 0),
+    ];
+    let mut asteroids: Vec<(Vec2, Vec2, f64)> = world
+        .asteroids
+        .values()
+        .map(|asteroid| {
+            let offset = me.pos.wrapped_delta(asteroid.pos, world.bounds);
+            let velocity = asteroid.velocity() - me.velocity();
+            (offset, velocity, asteroid.scale)
+        })
+        .collect();
+    asteroids.sort_by(|a, b| a.0.length_squared().total_cmp(&b.0.length_squared()));
+    for i in 0..nearest {
+        match asteroids.get(i) {
+            Some((offset, velocity, scale)) => {
+                let offset = offset.rotate(-me.rot) / PLAYFIELD.x;
+                let velocity = velocity.rotate(-me.rot) / SPEED_UNIT;
+                features.extend([offset.x, offset.y, velocity.x, velocity.y]);
+                features.push(scale / PLAYFIELD.x);
+            }
+            None => features.extend([0.0; PER_ASTEROID]),
+        }
+    }
+    for ray in cast(observation, rays, RAY_RANGE) {
+        let asteroid = match ray.hit {
+            Some(Hit::Asteroid(tier)) => (tier + 1) as f64 / 3.0,
+            _ => 0.0,
+        };
+        features.extend([
+            ray.distance / RAY_RANGE,
+            asteroid,
+            flag(ray.hit == Some(Hit::Ship)),
+            flag(ray.hit == Some(Hit::Bullet)),
+        ]);
+    }
+    features.into_iter().map(|value| value as f32).collect()
+}
+
+/// What the player's screen shows, shrunk to `width` by `height` grey
+/// pixels.
+pub fn pixels(world: &World, width: usize, height: usize) -> Vec<u8> {
+    let mut frame = Framebuffer::new(width, height);
+    let output = Vec2::new(width as f64, height as f64);
+    let viewport = Viewport::fit(world.camera.view, output, Scaling::Stretched);
+    frame.clear(Color::BLACK);
+    world.draw(&mut viewport.transform(&mut frame));
+    frame
+        .pixels()
+        .chunks(4)
+        .map(|pixel| pixel[0].max(pixel[1]).max(pixel[2]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn episodes_repeat_and_end() {
+        let mut env = Env::new(EnvConfig {
+            pixels: Some((32, 24)),
+            rays: 16,
+            ..EnvConfig::default()
+        });
+        let first = env.reset(9);
+        assert_eq!(first.features.len(), env.feature_count());
+        assert_eq!(first.pixels.as_ref().unwrap().len(), 32 * 24);
+        assert!(first.pixels.unwrap().iter().any(|pixel| *pixel > 0));
+        let fire = ShipInput {
+            fire: true,
+            left: true,
+            ..ShipInput::default()
+        };
+        let play = |env: &mut Env| {
+            let mut total = 0.0;
+            let mut steps = 0;
+            loop {
+                let step = env.step(fire);
+                total += step.reward;
+                steps += 1;
+                if step.done {
+                    return (total, steps, step.features);
+                }
+            }
+        };
+        let played = play(&mut env);
+        assert!(played.0 > 0.0);
+        env.reset(9);
+        assert_eq!(play(&mut env), played);
+        // Cut short, the episode says so.
+        env.config.max_frames = 40;
+        env.reset(9);
+        let steps: Vec<Step> = (0..10).map(|_| env.step(fire)).collect();
+        assert!(!steps[8].done && steps[9].done && steps[9].truncated);
+    }
+}
@@ -0,0 +1,203 @@
+//! A program speaking `protocol` flying a ship in someone else's game. The
+//! game runs its own clock, so the agent's requests only ever see the game it
+//! is in: a reset is answered with the ship as it is, whatever the seed, and
+//! a step with how it fared `frame_skip` frames later.
+
+use super::protocol::{self, Reply};
+use super::{features, pixels, EnvConfig, Step};
+use crate::control::{Observation, ShipController};
+use crate::input::ShipInput;
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, BufWriter};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+// How long an agent may think before its ship is left alone.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An agent program, spawned once and kept for game after game.
+pub struct Agent {
+    config: EnvConfig,
+    command: String,
+    child: Child,
+    // Lines read from the agent on a thread of their own, so a hung agent
+    // cannot hang the game.
+    requests: Receiver<io::Result<String>>,
+    timeout: Duration,
+    replies: BufWriter<ChildStdin>,
+    held: ShipInput,
+    // Frames left to hold `held` for.
+    frames: u32,
+    // The ship's score when the step being played was asked for.
+    pending: Option<u32>,
+    // Stopped answering, or answered nonsense; the ship is left alone.
+    broken: bool,
+}
+impl Agent {
+    /// Runs `command`, split on whitespace, observing as `config` says.
+    pub fn spawn(command: &str, config: EnvConfig) -> io::Result<Agent> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (send, requests) = mpsc::channel();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        std::thread::spawn(move || {
+            for line in stdout.lines() {
+                if send.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Agent {
+            config,
+            command: command.into(),
+            requests,
+            timeout: TIMEOUT,
+            replies: BufWriter::new(child.stdin.take().unwrap()),
+            child,
+            held: ShipInput::default(),
+            frames: 0,
+            pending: None,
+            broken: false,
+        })
+    }
+    fn step(&self, observation: &Observation, done: bool) -> Step {
+        let ship = observation.own();
+        let before = self.pending.unwrap_or(ship.score);
+        Step {
+            features: features(observation, self.config.nearest, self.config.rays),
+            pixels: self
+                .config
+                .pixels
+                .map(|(width, height)| pixels(observation.world, width, height)),
+            reward: (ship.score - before) as f64,
+            done,
+            truncated: done && (ship.alive || ship.respawn > 0),
+        }
+    }
+    // Answers the step being played, then requests until the next step.
+    fn decide(&mut self, observation: &Observation) -> Result<ShipInput, String> {
+        if self.pending.is_some() {
+            let step = self.step(observation, false);
+            self.pending = None;
+            self.reply(&Reply::new(observation, &step))?;
+        }
+        loop {
+            let line = match self.requests.recv_timeout(self.timeout) {
+                Ok(Ok(line)) => line,
+                Ok(Err(error)) => return Err(error.to_string()),
+                Err(RecvTimeoutError::Timeout) => return Err("stopped answering".into()),
+                Err(RecvTimeoutError::Disconnected) => return Err("hung up".into()),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request: Value = match serde_json::from_str(&line) {
+                Ok(request) => request,
+                Err(error) => {
+                    self.reply(&protocol::error(error))?;
+                    continue;
+                }
+            };
+            match request["op"].as_str() {
+                Some("spec") => self.reply(&protocol::spec(&self.config))?,
+                Some("reset") => {
+                    let step = self.step(observation, false);
+                    self.reply(&Reply::new(observation, &step).reset())?;
+                }
+                Some("step") => match protocol::action(&request["action"]) {
+                    Ok(input) => {
+                        self.pending = Some(observation.own().score);
+                        return Ok(input);
+                    }
+                    Err(error) => self.reply(&protocol::error(error))?,
+                },
+                Some("close") => return Err("closed".into()),
+                _ => self.reply(&protocol::error("op must be spec, reset, step or close"))?,
+            }
+        }
+    }
+    fn reply(&mut self, reply: &impl serde::Serialize) -> Result<(), String> {
+        protocol::send(&mut self.replies, reply).map_err(|error| error.to_string())
+    }
+    fn broke(&mut self, error: String) {
+        eprintln!("{}: {}; leaving its ship alone", self.command, error);
+        self.broken = true;
+    }
+}
+impl ShipController for Agent {
+    fn command(&mut self, observation: &Observation) -> ShipInput {
+        if self.broken {
+            return ShipInput::default();
+        }
+        // Out of lives is the end of the agent's episode, as in `Env`.
+        let ship = observation.own();
+        if !ship.alive && ship.respawn == 0 {
+            self.finish(observation);
+            return ShipInput::default();
+        }
+        if self.frames > 0 {
+            self.frames -= 1;
+            return self.held;
+        }
+        match self.decide(observation) {
+            Ok(input) => {
+                self.held = input;
+                self.frames = self.config.frame_skip.max(1) - 1;
+                input
+            }
+            Err(error) => {
+                self.broke(error);
+                ShipInput::default()
+            }
+        }
+    }
+    fn finish(&mut self, observation: &Observation) {
+        self.frames = 0;
+        if self.broken || self.pending.is_none() {
+            return;
+        }
+        let step = self.step(observation, true);
+        self.pending = None;
+        if let Err(error) = self.reply(&Reply::new(observation, &step)) {
+            self.broke(error);
+        }
+    }
+}
+impl Drop for Agent {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::settings::Rules;
+    use crate::world::World;
+    use std::time::Instant;
+
+    #[test]
+    fn hung_agents_are_left_alone() {
+        let mut agent = Agent::spawn("sleep 30", EnvConfig::default()).unwrap();
+        agent.timeout = Duration::from_millis(100);
+        let world = World::with_rules(Rules::default(), 1);
+        let observation = Observation {
+            world: &world,
+            ship: 0,
+        };
+        let started = Instant::now();
+        assert_eq!(agent.command(&observation), ShipInput::default());
+        assert!(agent.broken);
+        assert_eq!(agent.command(&observation), ShipInput::default());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,121 @@
+//! The protocol `foxtroids-env` speaks, one JSON object per line each way.
+//! Agents send requests and the game answers each:
+//!
+//! ```text
+//! {"op": "spec"}                       what observations and actions look like
+//! {"op": "reset", "seed": 7}           a new episode; the seed is optional
+//! {"op": "step", "action": 17}         buttons as bits, or as an object like
+//!                                      {"fire": true, "left": true}
+//! {"op": "close"}                      no answer; the agent is done
+//! ```
+//!
+//! Resets answer with `obs` and `info`, steps with `obs`, `reward`, `done`
+//! and `info`. Bad requests are answered with `error` and otherwise ignored.
+
+use super::{EnvConfig, Step};
+use crate::control::Observation;
+use crate::input::ShipInput;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// Button names, in `ShipInput::buttons` order; an action number holds
+/// button `i` when bit `i` is set.
+pub const BUTTONS: [&str; 6] = ["left", "right", "thrust", "reverse", "fire", "hyperspace"];
+
+/// The buttons in a step request's `action`, as bits or by name.
+pub fn action(value: &Value) -> Result<ShipInput, String> {
+    if let Some(bits) = value.as_u64().filter(|bits| *bits < 64) {
+        return Ok(ShipInput::from_bits(bits as u8));
+    }
+    let held = value
+        .as_object()
+        .ok_or("action must be a number below 64 or an object of buttons")?;
+    if let Some(name) = held.keys().find(|name| !BUTTONS.contains(&name.as_str())) {
+        return Err(format!("unknown button {}", name));
+    }
+    Ok(ShipInput::from_buttons(BUTTONS.map(|name| {
+        held.get(name).and_then(Value::as_bool).unwrap_or(false)
+    })))
+}
+
+/// The answer to `spec`.
+pub fn spec(config: &EnvConfig) -> Value {
+    json!({
+        "features": super::feature_count(config.nearest, config.rays),
+        "nearest": config.nearest,
+        "rays": config.rays,
+        "pixels": config.pixels.map(|(width, height)| [width, height]),
+        "frame_skip": config.frame_skip,
+        "buttons": BUTTONS,
+    })
+}
+
+/// The answer to a bad request.
+pub fn error(message: impl Display) -> Value {
+    json!({ "error": message.to_string() })
+}
+
+/// The answer to a reset or a step: what the observed ship sees now.
+#[derive(Serialize)]
+pub struct Reply<'a> {
+    obs: Obs<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reward: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    done: Option<bool>,
+    info: Info,
+}
+#[derive(Serialize)]
+struct Obs<'a> {
+    features: &'a [f32],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pixels: Option<&'a [u8]>,
+}
+#[derive(Serialize)]
+struct Info {
+    frame: u64,
+    score: u32,
+    spare: u32,
+    alive: bool,
+    asteroids: usize,
+    truncated: bool,
+}
+impl Reply<'_> {
+    /// The answer to a step that ended in `step`.
+    pub fn new<'a>(observation: &Observation, step: &'a Step) -> Reply<'a> {
+        let ship = observation.own();
+        Reply {
+            obs: Obs {
+                features: &step.features,
+                pixels: step.pixels.as_deref(),
+            },
+            reward: Some(step.reward),
+            done: Some(step.done),
+            info: Info {
+                frame: observation.world.frame,
+                score: ship.score,
+                spare: ship.spare,
+                alive: ship.alive,
+                asteroids: observation.world.asteroids.len(),
+                truncated: step.truncated,
+            },
+        }
+    }
+    /// The same as the answer to a reset, which has no reward yet.
+    pub fn reset(self) -> Self {
+        Reply {
+            reward: None,
+            done: None,
+            ..self
+        }
+    }
+}
+
+/// Writes `reply` as one line and sends it on its way.
+pub fn send(out: &mut impl Write, reply: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, reply)?;
+    writeln!(out)?;
+    out.flush()
+}
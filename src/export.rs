@@ -0,0 +1,374 @@
+//! Exporting frames as SVG and replays as animations.
+
+use crate::math::Vec2;
+use crate::render::software::Framebuffer;
+use crate::render::svg::Svg;
+use crate::render::{Color, Renderer, Transform};
+use crate::replay::Replay;
+use crate::shape::Shape;
+use crate::world::{World, PLAYFIELD};
+use std::io::{self, Write};
+
+/// The frame as an SVG document the size of the screen.
+pub fn frame_svg(world: &World, stroke_width: f64) -> String {
+    let mut svg = Svg::new(world.camera.view.x, world.camera.view.y, stroke_width);
+    svg.clear(Color::BLACK);
+    world.draw(&mut svg);
+    svg.finish()
+}
+/// One shape's outline, unrotated and centred in a document just big enough
+/// for it, with the shape's colour and size.
+pub fn outline_svg(shape: &Shape, stroke_width: f64) -> String {
+    let half = shape.scale + stroke_width;
+    let mut svg = Svg::new(half * 2.0, half * 2.0, stroke_width);
+    svg.clear(Color::BLACK);
+    svg.set_color(shape.color);
+    let points: Vec<Vec2> = shape
+        .outline
+        .iter()
+        .map(|p| *p * shape.scale + Vec2::new(half, half))
+        .collect();
+    svg.polygon(&points);
+    svg.finish()
+}
+
+// Palette index left unused by colours and marking pixels unchanged since the
+// previous frame.
+const TRANSPARENT: u8 = 255;
+
+/// Which part of a replay to export and how. Frame N is the picture after N
+/// ticks; `from..to` selects frames and `every` keeps every Nth of them.
+#[derive(Clone, Debug)]
+pub struct GifOptions {
+    pub from: u64,
+    /// Exclusive; `None` runs to the end of the replay.
+    pub to: Option<u64>,
+    /// Size relative to the playfield.
+    pub scale: f64,
+    pub every: u64,
+}
+impl Default for GifOptions {
+    // Every second tick, 30 frames per second: browsers slow down GIFs with
+    // frames shorter than 2/100 s.
+    fn default() -> GifOptions {
+        GifOptions {
+            from: 0,
+            to: None,
+            scale: 1.0,
+            every: 2,
+        }
+    }
+}
+
+/// Re-simulates `replay` and calls `f` with each selected frame.
+fn for_each_frame(
+    replay: &Replay,
+    options: &GifOptions,
+    mut f: impl FnMut(&Framebuffer) -> io::Result<()>,
+) -> io::Result<()> {
+    let size = PLAYFIELD * options.scale;
+    let every = options.every.max(1);
+    let mut fb = Framebuffer::new(
+        size.x.round().max(1.0) as usize,
+        size.y.round().max(1.0) as usize,
+    );
+    let mut world = replay.world();
+    while world.frame < options.to.unwrap_or(u64::MAX) {
+        if world.frame >= options.from && (world.frame - options.from).is_multiple_of(every) {
+            fb.clear(Color::BLACK);
+            world.draw(&mut Transform {
+                inner: &mut fb,
+                scale: options.scale,
+                offset: Vec2::ZERO,
+            });
+            f(&fb)?;
+        }
+        match replay.input(world.frame) {
+            Some(inputs) => world.step(inputs),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+// Colours are binned to 4 bits per channel; a bin's index packs r, g and b.
+fn bin(pixel: &[u8]) -> usize {
+    (pixel[0] as usize >> 4) << 8 | (pixel[1] as usize >> 4) << 4 | pixel[2] as usize >> 4
+}
+
+/// Up to 255 colours picked from the bins used most, and the nearest of them
+/// for every bin.
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    lookup: Vec<u8>,
+}
+impl Palette {
+    /// `counts` and `sums` are per bin: pixels seen and their summed channels.
+    fn new(counts: &[u64], sums: &[[u64; 3]]) -> Palette {
+        let mut used: Vec<usize> = (0..counts.len()).filter(|&b| counts[b] > 0).collect();
+        used.sort_by_key(|&b| std::cmp::Reverse(counts[b]));
+        used.truncate(TRANSPARENT as usize);
+        let colors: Vec<[u8; 3]> = used
+            .iter()
+            .map(|&b| sums[b].map(|sum| (sum / counts[b]) as u8))
+            .collect();
+        let lookup = (0..counts.len())
+            .map(|b| {
+                let center = [b >> 8, b >> 4 & 15, b & 15].map(|c| (c * 16 + 8) as i32);
+                let distance = |color: &[u8; 3]| -> i32 {
+                    (0..3).map(|i| (color[i] as i32 - center[i]).pow(2)).sum()
+                };
+                match used.iter().position(|&u| u == b) {
+                    Some(i) => i as u8,
+                    None => (0..colors.len())
+                        .min_by_key(|&i| distance(&colors[i]))
+                        .unwrap_or(0) as u8,
+                }
+            })
+            .collect();
+        Palette { colors, lookup }
+    }
+    fn index(&self, fb: &Framebuffer) -> Vec<u8> {
+        fb.pixels()
+            .chunks_exact(4)
+            .map(|p| self.lookup[bin(p)])
+            .collect()
+    }
+    // The global colour table: a power of two entries of RGB.
+    fn table(&self) -> Vec<u8> {
+        let mut table: Vec<u8> = self.colors.iter().flatten().copied().collect();
+        table.resize(256 * 3, 0);
+        table
+    }
+}
+
+fn gif_error(error: gif::EncodingError) -> io::Error {
+    match error {
+        gif::EncodingError::Io(error) => error,
+        other => io::Error::new(io::ErrorKind::InvalidInput, other),
+    }
+}
+
+/// Writes part of `replay` as a looping GIF with one shared palette. After
+/// the first frame only the rectangle that changed is stored, with unchanged
+/// pixels inside it transparent, and repeated frames just lengthen the one
+/// before.
+pub fn replay_to_gif(replay: &Replay, options: &GifOptions, out: impl Write) -> io::Result<()> {
+    let invalid = |message| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    // Checked before rendering, so a huge scale fails without allocating.
+    let size = PLAYFIELD * options.scale;
+    if !(size.x.round() <= u16::MAX as f64 && size.y.round() <= u16::MAX as f64) {
+        return invalid("too large for a GIF");
+    }
+    let mut counts = vec![0; 4096];
+    let mut sums = vec![[0; 3]; 4096];
+    let (mut width, mut height) = (0, 0);
+    for_each_frame(replay, options, |fb| {
+        (width, height) = (fb.width, fb.height);
+        for p in fb.pixels().chunks_exact(4) {
+            counts[bin(p)] += 1;
+            for c in 0..3 {
+                sums[bin(p)][c] += p[c] as u64;
+            }
+        }
+        Ok(())
+    })?;
+    if width == 0 {
+        return invalid("no frames selected");
+    }
+    let palette = Palette::new(&counts, &sums);
+    let mut encoder =
+        gif::Encoder::new(out, width as u16, height as u16, &palette.table()).map_err(gif_error)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(gif_error)?;
+
+    // Delays are in hundredths of a second; rounding the running total keeps
+    // the clip at the game's speed.
+    let ticks_to_cs = |ticks: u64| (ticks * 100 + 30) / 60;
+    let every = options.every.max(1);
+    let mut previous: Option<Vec<u8>> = None;
+    let mut pending: Option<gif::Frame> = None;
+    let mut frames = 0;
+    for_each_frame(replay, options, |fb| {
+        let current = palette.index(fb);
+        let delay = (ticks_to_cs((frames + 1) * every) - ticks_to_cs(frames * every)) as u16;
+        frames += 1;
+        let frame = match &previous {
+            None => gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                buffer: current.clone().into(),
+                ..gif::Frame::default()
+            },
+            Some(previous) => {
+                let changed = |i: usize| current[i] != previous[i];
+                let rows: Vec<usize> = (0..height)
+                    .filter(|&y| (0..width).any(|x| changed(y * width + x)))
+                    .collect();
+                let (Some(&top), Some(&bottom)) = (rows.first(), rows.last()) else {
+                    if let Some(pending) = &mut pending {
+                        pending.delay += delay;
+                    }
+                    return Ok(());
+                };
+                let columns = |x: usize| (top..=bottom).any(|y| changed(y * width + x));
+                let left = (0..width).find(|&x| columns(x)).unwrap();
+                let right = (0..width).rev().find(|&x| columns(x)).unwrap();
+                let mut buffer = Vec::with_capacity((right - left + 1) * (bottom - top + 1));
+                for y in top..=bottom {
+                    for x in left..=right {
+                        let i = y * width + x;
+                        buffer.push(if changed(i) { current[i] } else { TRANSPARENT });
+                    }
+                }
+                gif::Frame {
+                    left: left as u16,
+                    top: top as u16,
+                    width: (right - left + 1) as u16,
+                    height: (bottom - top + 1) as u16,
+                    transparent: Some(TRANSPARENT),
+                    buffer: buffer.into(),
+                    ..gif::Frame::default()
+                }
+            }
+        };
+        if let Some(pending) = pending.replace(gif::Frame {
+            delay,
+            dispose: gif::DisposalMethod::Keep,
+            ..frame
+        }) {
+            encoder.write_frame(&pending).map_err(gif_error)?;
+        }
+        previous = Some(current);
+        Ok(())
+    })?;
+    if let Some(pending) = pending {
+        encoder.write_frame(&pending).map_err(gif_error)?;
+    }
+    encoder.into_inner().map_err(gif_error)?.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::ShipInput;
+
+    fn replay(ticks: usize) -> Replay {
+        let mut replay = Replay::new(3);
+        let turn = ShipInput {
+            left: true,
+            fire: true,
+            ..ShipInput::default()
+        };
+        replay.inputs = vec![vec![turn]; ticks];
+        replay
+    }
+
+    #[test]
+    fn selects_frames() {
+        let options = GifOptions {
+            from: 10,
+            to: Some(40),
+            scale: 0.5,
+            every: 10,
+        };
+        let mut sizes = Vec::new();
+        for_each_frame(&replay(100), &options, |fb| {
+            sizes.push((fb.width, fb.height));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sizes, [(300, 300); 3]);
+        let mut count = 0;
+        for_each_frame(
+            &replay(5),
+            &GifOptions {
+                every: 1,
+                ..GifOptions::default()
+            },
+            |_| {
+                count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        // The starting picture and one after each tick.
+        assert_eq!(count, 6);
+    }
+
+    #[test]
+    fn palette_keeps_common_colours_exact() {
+        let mut counts = vec![0; 4096];
+        let mut sums = vec![[0; 3]; 4096];
+        for (pixel, n) in [([0, 0, 0], 100), ([255, 255, 255], 10), ([250, 120, 10], 5)] {
+            counts[bin(&pixel)] += n;
+            sums[bin(&pixel)] = pixel.map(|c| c as u64 * n);
+        }
+        let palette = Palette::new(&counts, &sums);
+        assert_eq!(palette.colors, [[0, 0, 0], [255, 255, 255], [250, 120, 10]]);
+        assert_eq!(palette.lookup[bin(&[250, 250, 250])], 1);
+        assert_eq!(palette.lookup[bin(&[20, 10, 0])], 0);
+    }
+
+    #[test]
+    fn svg_frames_and_outlines() {
+        let world = replay(0).world();
+        let frame = frame_svg(&world, 1.0);
+        assert!(frame.contains("width=\"600\" height=\"600\""));
+        // The ship, the score and nothing else yet.
+        assert_eq!(frame.matches("<polygon").count(), 2);
+        let mut world = world;
+        let asteroid = world.spawn_asteroid();
+        let asteroid = world.asteroids.get(asteroid).unwrap();
+        let outline = outline_svg(asteroid, 2.0);
+        let size = ((asteroid.scale * 2.0 + 4.0) * 1000.0).round() / 1000.0;
+        assert!(outline.contains(&format!("viewBox=\"0 0 {} {}\"", size, size)));
+        assert_eq!(outline.matches("<polygon").count(), 1);
+        // One coordinate pair per vertex of the outline, all inside the picture.
+        let points = outline.split("points=\"").nth(1).unwrap();
+        let points: Vec<Vec<f64>> = points[..points.find('"').unwrap()]
+            .split(' ')
+            .map(|pair| pair.split(',').map(|n| n.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(points.len(), asteroid.outline.len());
+        assert!(points
+            .iter()
+            .all(|pair| pair.len() == 2 && pair.iter().all(|n| (0.0..=size).contains(n))));
+    }
+
+    #[test]
+    fn writes_a_decodable_gif() {
+        let mut out = Vec::new();
+        let options = GifOptions {
+            scale: 0.25,
+            ..GifOptions::default()
+        };
+        replay_to_gif(&replay(60), &options, &mut out).unwrap();
+        assert!(out.starts_with(b"GIF89a"));
+        let mut decoder = gif::DecodeOptions::new().read_info(&out[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (150, 150));
+        let mut frames = 0;
+        let mut duration = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames += 1;
+            duration += frame.delay as u32;
+        }
+        assert!(frames > 1 && frames <= 31);
+        // 62 ticks of picture (frame 60 shows for two ticks) at 60 per second.
+        assert_eq!(duration, 103);
+        let huge = GifOptions {
+            scale: 1e9,
+            ..GifOptions::default()
+        };
+        let empty = GifOptions {
+            from: 100,
+            ..options
+        };
+        for options in [huge, empty] {
+            let mut out = Vec::new();
+            assert!(replay_to_gif(&replay(60), &options, &mut out).is_err());
+            assert!(out.is_empty());
+        }
+    }
+}
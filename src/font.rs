@@ -0,0 +1,95 @@
+//! A small stroke font, so text is drawn with the same lines as everything else.
+
+use crate::math::Vec2;
+use crate::render::{Color, Renderer};
+
+// Stroke font on a 4x6 grid, y pointing down. Each glyph is a list of
+// polylines separated by spaces, every point written as two digits "xy".
+fn glyph(c: char) -> &'static str {
+    match c.to_ascii_uppercase() {
+        '0' => "0040460600 0640",
+        '1' => "112026 0646",
+        '2' => "004043030646",
+        '3' => "00404606 0343",
+        '4' => "000343 4046",
+        '5' => "400003434606",
+        '6' => "400006464303",
+        '7' => "004016",
+        '8' => "0040460600 0343",
+        '9' => "430300404606",
+        'A' => "0602204246 0343",
+        'B' => "003041423303 334445360600",
+        'C' => "40000646",
+        'D' => "00304244360600",
+        'E' => "40000646 0333",
+        'F' => "400006 0333",
+        'G' => "400006464323",
+        'H' => "0006 4046 0343",
+        'I' => "0040 2026 0646",
+        'J' => "4045361605",
+        'K' => "0006 400346",
+        'L' => "000646",
+        'M' => "0600234046",
+        'N' => "06004640",
+        'O' => "0040460600",
+        'P' => "0600404303",
+        'Q' => "0040460600 2446",
+        'R' => "060040430346",
+        'S' => "400003434606",
+        'T' => "0040 2026",
+        'U' => "00064640",
+        'V' => "002640",
+        'W' => "0006234640",
+        'X' => "0046 4006",
+        'Y' => "002340 2326",
+        'Z' => "00400646",
+        ':' => "2122 2425",
+        '.' => "2526",
+        ',' => "2516",
+        '-' => "1333",
+        '+' => "2125 1333",
+        '=' => "0242 0444",
+        '/' => "0640",
+        '_' => "0646",
+        '\'' => "2021",
+        '!' => "2024 2526",
+        '?' => "0040422324 2526",
+        '%' => "0640 0010 3646",
+        '[' => "30101636",
+        ']' => "10303616",
+        '(' => "30111536",
+        ')' => "10313516",
+        '<' => "400346",
+        '>' => "004306",
+        '`' => "1021",
+        ' ' => "",
+        _ => "0040460600",
+    }
+}
+
+const ADVANCE: f64 = 6.0;
+
+/// Line segments spelling `text` with its top-left corner at `origin`; `size`
+/// is the height of a capital letter in pixels.
+pub fn text_lines(text: &str, origin: Vec2, size: f64) -> Vec<(Vec2, Vec2)> {
+    let unit = size / 6.0;
+    let mut lines = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let corner = origin + Vec2::new(i as f64 * ADVANCE * unit, 0.0);
+        for stroke in glyph(c).split(' ').filter(|stroke| !stroke.is_empty()) {
+            let points: Vec<Vec2> = stroke
+                .as_bytes()
+                .chunks(2)
+                .map(|xy| corner + Vec2::new((xy[0] - b'0') as f64, (xy[1] - b'0') as f64) * unit)
+                .collect();
+            for pair in points.windows(2) {
+                lines.push((pair[0], pair[1]));
+            }
+        }
+    }
+    lines
+}
+pub fn draw_text(text: &str, origin: Vec2, size: f64, color: Color, r: &mut dyn Renderer) {
+    r.set_color(color);
+    r.text(text, origin, size);
+}
@@ -0,0 +1,66 @@
+//! What a ship is told to do each frame, independent of where it came from.
+
+use std::ops::BitOr;
+
+/// Buttons held for one frame. Opposite directions cancel each other out.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ShipInput {
+    /// Rotate counter-clockwise.
+    pub left: bool,
+    /// Rotate clockwise.
+    pub right: bool,
+    pub thrust: bool,
+    pub reverse: bool,
+    pub fire: bool,
+    /// Jump to a random spot, when the drive has recharged.
+    pub hyperspace: bool,
+}
+impl ShipInput {
+    /// The buttons in field order.
+    pub fn buttons(&self) -> [bool; 6] {
+        [
+            self.left,
+            self.right,
+            self.thrust,
+            self.reverse,
+            self.fire,
+            self.hyperspace,
+        ]
+    }
+    pub fn from_buttons(held: [bool; 6]) -> ShipInput {
+        let [left, right, thrust, reverse, fire, hyperspace] = held;
+        ShipInput {
+            left,
+            right,
+            thrust,
+            reverse,
+            fire,
+            hyperspace,
+        }
+    }
+    /// The buttons packed one per bit, in field order from the lowest.
+    pub fn bits(&self) -> u8 {
+        self.buttons()
+            .iter()
+            .enumerate()
+            .map(|(i, held)| (*held as u8) << i)
+            .sum()
+    }
+    pub fn from_bits(bits: u8) -> ShipInput {
+        ShipInput::from_buttons(std::array::from_fn(|i| bits & (1 << i) != 0))
+    }
+}
+/// Buttons held on either of two devices driving the same ship.
+impl BitOr for ShipInput {
+    type Output = ShipInput;
+    fn bitor(self, other: ShipInput) -> ShipInput {
+        ShipInput {
+            left: self.left || other.left,
+            right: self.right || other.right,
+            thrust: self.thrust || other.thrust,
+            reverse: self.reverse || other.reverse,
+            fire: self.fire || other.fire,
+            hyperspace: self.hyperspace || other.hyperspace,
+        }
+    }
+}
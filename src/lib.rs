@@ -0,0 +1,35 @@
+//! Foxtroids: an asteroids alike game. The simulation, drawing and tooling
+//! live here and build without any system libraries. The frontends sit behind
+//! features: the SDL window in `sdl`, the terminal game in `tui`.
+
+pub mod ai;
+pub mod arena;
+pub mod attract;
+pub mod audio;
+pub mod camera;
+pub mod capture;
+pub mod cli;
+#[cfg(feature = "dev")]
+pub mod console;
+pub mod control;
+pub mod debug;
+pub mod env;
+pub mod export;
+pub mod font;
+pub mod input;
+pub mod math;
+pub mod net;
+pub mod neuro;
+pub mod physics;
+pub mod render;
+pub mod replay;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+pub mod sensors;
+pub mod settings;
+pub mod shape;
+pub mod snapshot;
+pub mod tournament;
+#[cfg(feature = "terminal")]
+pub mod tui;
+pub mod world;
@@ -0,0 +1,216 @@
+//! 2D vector maths.
+
+use std::f64::consts::TAU;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// A 2D vector, used for positions, offsets and velocities alike.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+
+    pub fn new(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+    /// Unit vector pointing along `angle` (radians, +x is 0, +y is a quarter turn).
+    pub fn from_angle(angle: f64) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(cos, sin)
+    }
+    pub fn dot(self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+    /// z component of the 3D cross product; positive when `other` is clockwise
+    /// of `self` on screen (y points down).
+    pub fn cross(self, other: Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
+    }
+    pub fn length(self) -> f64 {
+        self.length_squared().sqrt()
+    }
+    /// Same direction with length 1, or zero for the zero vector.
+    pub fn normalize(self) -> Vec2 {
+        let length = self.length();
+        if length == 0.0 {
+            Vec2::ZERO
+        } else {
+            self / length
+        }
+    }
+    pub fn angle(self) -> f64 {
+        self.y.atan2(self.x)
+    }
+    pub fn rotate(self, angle: f64) -> Vec2 {
+        self.rotate_by(Vec2::from_angle(angle))
+    }
+    /// Rotates by the angle of the unit vector `dir`; cheaper than `rotate`
+    /// when many points share one rotation.
+    pub fn rotate_by(self, dir: Vec2) -> Vec2 {
+        Vec2::new(
+            self.x * dir.x - self.y * dir.y,
+            self.x * dir.y + self.y * dir.x,
+        )
+    }
+    pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+        self + (other - self) * t
+    }
+    /// Shortest offset from `self` to `other` on a torus of size `bounds`.
+    pub fn wrapped_delta(self, other: Vec2, bounds: Vec2) -> Vec2 {
+        let wrap = |d: f64, size: f64| d - size * (d / size).round();
+        let d = other - self;
+        Vec2::new(wrap(d.x, bounds.x), wrap(d.y, bounds.y))
+    }
+    pub fn wrapped_distance(self, other: Vec2, bounds: Vec2) -> f64 {
+        self.wrapped_delta(other, bounds).length()
+    }
+    /// Brings a point back inside `[0, bounds)`.
+    pub fn wrap(self, bounds: Vec2) -> Vec2 {
+        Vec2::new(self.x.rem_euclid(bounds.x), self.y.rem_euclid(bounds.y))
+    }
+}
+/// Wraps an angle into `[0, TAU)`.
+pub fn wrap_angle(angle: f64) -> f64 {
+    angle.rem_euclid(TAU)
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+impl Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+impl Mul<f64> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, k: f64) -> Vec2 {
+        Vec2::new(self.x * k, self.y * k)
+    }
+}
+impl Mul<Vec2> for f64 {
+    type Output = Vec2;
+    fn mul(self, v: Vec2) -> Vec2 {
+        v * self
+    }
+}
+impl MulAssign<f64> for Vec2 {
+    fn mul_assign(&mut self, k: f64) {
+        *self = *self * k;
+    }
+}
+impl Div<f64> for Vec2 {
+    type Output = Vec2;
+    fn div(self, k: f64) -> Vec2 {
+        Vec2::new(self.x / k, self.y / k)
+    }
+}
+impl Neg for Vec2 {
+    type Output = Vec2;
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        (a - b).length() < 1e-9
+    }
+
+    #[test]
+    fn operators() {
+        let a = Vec2::new(1.0, 2.0);
+        let b = Vec2::new(3.0, -4.0);
+        assert_eq!(a + b, Vec2::new(4.0, -2.0));
+        assert_eq!(a - b, Vec2::new(-2.0, 6.0));
+        assert_eq!(a * 2.0, Vec2::new(2.0, 4.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(b / 2.0, Vec2::new(1.5, -2.0));
+        assert_eq!(-a, Vec2::new(-1.0, -2.0));
+        let mut c = a;
+        c += b;
+        c -= a;
+        c *= 3.0;
+        assert_eq!(c, b * 3.0);
+    }
+
+    #[test]
+    fn dot_and_cross() {
+        let x = Vec2::new(1.0, 0.0);
+        let y = Vec2::new(0.0, 1.0);
+        assert_eq!(x.dot(y), 0.0);
+        assert_eq!(x.dot(x), 1.0);
+        assert_eq!(x.cross(y), 1.0);
+        assert_eq!(y.cross(x), -1.0);
+        assert_eq!(Vec2::new(2.0, 3.0).cross(Vec2::new(4.0, 6.0)), 0.0);
+    }
+
+    #[test]
+    fn length_and_normalize() {
+        let v = Vec2::new(3.0, 4.0);
+        assert_eq!(v.length_squared(), 25.0);
+        assert_eq!(v.length(), 5.0);
+        assert!(close(v.normalize(), Vec2::new(0.6, 0.8)));
+        assert_eq!(Vec2::ZERO.normalize(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn angles() {
+        assert!(close(Vec2::from_angle(0.0), Vec2::new(1.0, 0.0)));
+        assert!(close(Vec2::from_angle(FRAC_PI_2), Vec2::new(0.0, 1.0)));
+        assert!(close(Vec2::new(1.0, 0.0).rotate(PI), Vec2::new(-1.0, 0.0)));
+        assert!(close(
+            Vec2::new(2.0, 1.0).rotate(0.7),
+            Vec2::new(2.0, 1.0).rotate_by(Vec2::from_angle(0.7))
+        ));
+        assert!((Vec2::from_angle(1.2).angle() - 1.2).abs() < 1e-12);
+        assert!((wrap_angle(-FRAC_PI_2) - 3.0 * FRAC_PI_2).abs() < 1e-12);
+        let v = Vec2::new(5.0, -2.0);
+        assert!((v.rotate(1.0).length() - v.length()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Vec2::new(0.0, 10.0);
+        let b = Vec2::new(10.0, 0.0);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Vec2::new(5.0, 5.0));
+    }
+
+    #[test]
+    fn wrapped_distance() {
+        let bounds = Vec2::new(600.0, 400.0);
+        let a = Vec2::new(10.0, 10.0);
+        let b = Vec2::new(590.0, 390.0);
+        assert!(close(a.wrapped_delta(b, bounds), Vec2::new(-20.0, -20.0)));
+        assert!(close(b.wrapped_delta(a, bounds), Vec2::new(20.0, 20.0)));
+        assert!((a.wrapped_distance(b, bounds) - 800f64.sqrt()).abs() < 1e-9);
+        let c = Vec2::new(300.0, 200.0);
+        assert!(close(a.wrapped_delta(c, bounds), c - a));
+        assert_eq!(Vec2::new(-10.0, 410.0).wrap(bounds), Vec2::new(590.0, 10.0));
+    }
+}
@@ -0,0 +1,404 @@
+//! Online play, peer to peer over UDP with input delay and rollback. Every
+//! peer runs the whole simulation and only inputs cross the network. Until
+//! a peer's input for a frame arrives it is predicted to be the same as its
+//! last one; when the real input turns out different, the world is restored
+//! from a snapshot and the frames since are simulated again.
+
+pub mod spectate;
+pub mod transport;
+
+use crate::input::ShipInput;
+use crate::replay::Replay;
+use crate::world::World;
+pub use spectate::{Broadcaster, Spectator};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+pub use transport::{Conditions, LossyTransport, Transport, UdpTransport};
+
+const MAGIC: &[u8; 3] = b"FX1";
+// Most inputs resent in one packet; well inside a UDP datagram.
+const MAX_INPUTS: usize = 255;
+// Confirmed frames between checksums.
+const CHECKSUM_EVERY: u64 = 10;
+// Packets claiming frames this far ahead of ours are ignored.
+const MAX_AHEAD: u64 = 600;
+
+/// How a netplay session trades responsiveness for rollbacks.
+#[derive(Clone, Debug)]
+pub struct NetConfig {
+    /// Frames between reading the local input and the ship acting on it,
+    /// which gives it time to reach the other peers.
+    pub input_delay: u64,
+    /// Furthest the simulation runs past the last frame with every peer's
+    /// input; beyond that it waits for the network.
+    pub max_rollback: u64,
+}
+impl Default for NetConfig {
+    fn default() -> NetConfig {
+        NetConfig {
+            input_delay: 2,
+            max_rollback: 8,
+        }
+    }
+}
+
+/// What a peer sends the others every tick.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Packet {
+    pub player: usize,
+    /// Frames of the receiver's input the sender has, counted from frame 0
+    /// without gaps; the receiver resends from there.
+    pub ack: u64,
+    /// A confirmed frame and the sender's checksum of the world before it.
+    pub checksum: Option<(u64, u64)>,
+    /// The sender's inputs, the first one for frame `start`.
+    pub start: u64,
+    pub inputs: Vec<ShipInput>,
+}
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.player as u8);
+        bytes.extend_from_slice(&self.ack.to_le_bytes());
+        let (frame, checksum) = self.checksum.unwrap_or((u64::MAX, 0));
+        bytes.extend_from_slice(&frame.to_le_bytes());
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes.extend_from_slice(&self.start.to_le_bytes());
+        bytes.push(self.inputs.len() as u8);
+        bytes.extend(self.inputs.iter().map(ShipInput::bits));
+        bytes
+    }
+    /// `None` for anything that is not a whole packet.
+    pub fn decode(bytes: &[u8]) -> Option<Packet> {
+        let rest = bytes.strip_prefix(MAGIC)?;
+        let (&player, rest) = rest.split_first()?;
+        let mut words = rest
+            .chunks(8)
+            .take(4)
+            .map(|chunk| Some(u64::from_le_bytes(chunk.try_into().ok()?)));
+        let mut word = || words.next().flatten();
+        let (ack, frame, checksum, start) = (word()?, word()?, word()?, word()?);
+        let (&count, inputs) = rest.get(32..)?.split_first()?;
+        if inputs.len() != count as usize {
+            return None;
+        }
+        Some(Packet {
+            player: player as usize,
+            ack,
+            checksum: (frame != u64::MAX).then_some((frame, checksum)),
+            start,
+            inputs: inputs
+                .iter()
+                .map(|bits| ShipInput::from_bits(*bits))
+                .collect(),
+        })
+    }
+}
+
+/// Two peers disagree about the world before `frame`: they have gone out
+/// of sync, and the game means something different on each screen.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Desync {
+    pub frame: u64,
+    /// The peer whose checksum differs from ours.
+    pub player: usize,
+}
+
+/// How a game reaches or leaves the network, if it does.
+pub enum Link {
+    /// Playing online with others.
+    Peers(Box<NetSession>),
+    /// Playing locally while spectators watch.
+    Broadcast(Broadcaster),
+    /// Watching someone else's game.
+    Watch(Box<Spectator>),
+}
+
+/// One peer's side of an online game.
+pub struct NetSession {
+    local: usize,
+    config: NetConfig,
+    transport: Box<dyn Transport>,
+    world: World,
+    // The world before each frame from `snapshots_from` up to the current
+    // one, to roll back to.
+    snapshots: VecDeque<World>,
+    snapshots_from: u64,
+    // Every player's input for each frame, where known.
+    inputs: Vec<Vec<Option<ShipInput>>>,
+    // The inputs each simulated frame was last run with.
+    used: Vec<Vec<ShipInput>>,
+    // Per player, frames of their input known from frame 0 without gaps.
+    received: Vec<u64>,
+    // Per player, frames of our input they have told us they have.
+    acked: Vec<u64>,
+    // The earliest frame simulated with a wrong prediction.
+    rewind: Option<u64>,
+    // Our checksums of confirmed frames, the latest of them, the next frame
+    // to check, and the peers' checksums we could not compare yet.
+    checksums: HashMap<u64, u64>,
+    latest: Option<(u64, u64)>,
+    next_checksum: u64,
+    pending: Vec<(usize, u64, u64)>,
+    desync: Option<Desync>,
+    /// Rollbacks so far; each re-simulates one or more frames.
+    pub rollbacks: u64,
+}
+impl NetSession {
+    /// Plays `world`, which must be the same fresh world on every peer, as
+    /// player `local`.
+    pub fn new(
+        world: World,
+        local: usize,
+        transport: Box<dyn Transport>,
+        config: NetConfig,
+    ) -> NetSession {
+        let players = world.ships.len();
+        assert!(local < players && world.frame == 0);
+        // Nobody has input for the first frames; everyone starts idle.
+        let inputs = vec![vec![Some(ShipInput::default()); players]; config.input_delay as usize];
+        NetSession {
+            local,
+            received: vec![config.input_delay; players],
+            acked: vec![config.input_delay; players],
+            config,
+            transport,
+            world,
+            snapshots: VecDeque::new(),
+            snapshots_from: 0,
+            inputs,
+            used: Vec::new(),
+            rewind: None,
+            checksums: HashMap::new(),
+            latest: None,
+            next_checksum: 0,
+            pending: Vec::new(),
+            desync: None,
+            rollbacks: 0,
+        }
+    }
+    /// The world as this peer currently sees it, predictions included.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+    pub fn local(&self) -> usize {
+        self.local
+    }
+    /// Frames with every player's input known; they will not change again.
+    pub fn confirmed(&self) -> u64 {
+        self.received.iter().copied().min().unwrap_or(0)
+    }
+    /// The first disagreement found with another peer, if any.
+    pub fn desync(&self) -> Option<Desync> {
+        self.desync
+    }
+    /// Our checksum of the world before `frame`, once that is confirmed;
+    /// taken every few frames.
+    pub fn checksum(&self, frame: u64) -> Option<u64> {
+        self.checksums.get(&frame).copied()
+    }
+    /// The confirmed part of the game as a replay.
+    pub fn replay(&self) -> Replay {
+        let mut replay = Replay::new(self.world.seed);
+        replay.rules = self.world.rules.clone();
+        replay.inputs = self.inputs[..self.confirmed() as usize]
+            .iter()
+            .map(|frame| frame.iter().map(|input| input.unwrap()).collect())
+            .collect();
+        replay
+    }
+    /// Runs one network tick with `input` as the local player's buttons:
+    /// takes in the peers' packets, rolls back if they prove a prediction
+    /// wrong, sends ours and simulates a frame. Returns false when it had to
+    /// wait for the peers instead.
+    pub fn tick(&mut self, input: ShipInput) -> io::Result<bool> {
+        let target = self.world.frame + self.config.input_delay;
+        if self.received[self.local] == target {
+            self.set_input(target, self.local, input);
+        }
+        self.poll()?;
+        let ready = self.world.frame < self.confirmed() + self.config.max_rollback;
+        if ready {
+            self.advance();
+        }
+        Ok(ready)
+    }
+    /// Everything `tick` does except simulating a new frame: keeps the
+    /// peers up to date, e.g. while the game is paused or over.
+    pub fn poll(&mut self) -> io::Result<()> {
+        while let Some(bytes) = self.transport.recv()? {
+            if let Some(packet) = Packet::decode(&bytes) {
+                self.receive(packet);
+            }
+        }
+        if let Some(frame) = self.rewind.take() {
+            let now = self.world.frame;
+            let index = (frame - self.snapshots_from) as usize;
+            self.world = self.snapshots[index].clone();
+            self.snapshots.truncate(index);
+            while self.world.frame < now {
+                self.advance();
+            }
+            self.rollbacks += 1;
+        }
+        self.check();
+        self.send()
+    }
+    fn set_input(&mut self, frame: u64, player: usize, input: ShipInput) {
+        let frame = frame as usize;
+        if self.inputs.len() <= frame {
+            self.inputs
+                .resize(frame + 1, vec![None; self.received.len()]);
+        }
+        self.inputs[frame][player] = Some(input);
+        let received = &mut self.received[player];
+        while self
+            .inputs
+            .get(*received as usize)
+            .is_some_and(|inputs| inputs[player].is_some())
+        {
+            *received += 1;
+        }
+    }
+    fn receive(&mut self, packet: Packet) {
+        let player = packet.player;
+        if player >= self.received.len()
+            || player == self.local
+            || packet.start > self.world.frame + MAX_AHEAD
+        {
+            return;
+        }
+        self.acked[player] = self.acked[player].max(packet.ack);
+        for (frame, input) in (packet.start..).zip(packet.inputs) {
+            let known = self
+                .inputs
+                .get(frame as usize)
+                .is_some_and(|inputs| inputs[player].is_some());
+            if known {
+                continue;
+            }
+            self.set_input(frame, player, input);
+            let mispredicted = self
+                .used
+                .get(frame as usize)
+                .is_some_and(|used| used[player] != input);
+            if mispredicted {
+                self.rewind = Some(self.rewind.map_or(frame, |rewind| rewind.min(frame)));
+            }
+        }
+        if let Some((frame, checksum)) = packet.checksum {
+            self.pending.push((player, frame, checksum));
+        }
+    }
+    // The inputs to simulate `frame` with: what is known, or else each
+    // player's last known input.
+    fn frame_inputs(&self, frame: u64) -> Vec<ShipInput> {
+        (0..self.received.len())
+            .map(|player| {
+                let known = self
+                    .inputs
+                    .get(frame as usize)
+                    .and_then(|inputs| inputs[player]);
+                let last = self.received[player].checked_sub(1);
+                known
+                    .or_else(|| last.and_then(|last| self.inputs[last as usize][player]))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+    fn advance(&mut self) {
+        let frame = self.world.frame;
+        self.snapshots.push_back(self.world.clone());
+        let inputs = self.frame_inputs(frame);
+        self.world.step(&inputs);
+        self.used.truncate(frame as usize);
+        self.used.push(inputs);
+    }
+    // Checksums newly confirmed frames, compares them with the peers', and
+    // drops snapshots that can no longer be rolled back to.
+    fn check(&mut self) {
+        let settled = self.confirmed().min(self.world.frame);
+        while self.next_checksum <= settled {
+            let frame = self.next_checksum;
+            if frame.is_multiple_of(CHECKSUM_EVERY) {
+                let world = match self.snapshots.get((frame - self.snapshots_from) as usize) {
+                    Some(world) => world,
+                    None => &self.world,
+                };
+                let checksum = world.checksum();
+                self.checksums.insert(frame, checksum);
+                self.latest = Some((frame, checksum));
+            }
+            self.next_checksum += 1;
+        }
+        let checksums = &self.checksums;
+        let desync = &mut self.desync;
+        self.pending
+            .retain(|&(player, frame, theirs)| match checksums.get(&frame) {
+                Some(ours) => {
+                    if *ours != theirs && desync.is_none() {
+                        *desync = Some(Desync { frame, player });
+                    }
+                    false
+                }
+                None => true,
+            });
+        while self.snapshots_from < settled {
+            self.snapshots.pop_front();
+            self.snapshots_from += 1;
+        }
+    }
+    // Sends every peer the inputs it has not acknowledged yet.
+    fn send(&mut self) -> io::Result<()> {
+        let local = self.received[self.local];
+        for peer in 0..self.received.len() {
+            if peer == self.local {
+                continue;
+            }
+            let start = self.acked[peer].min(local);
+            let end = local.min(start + MAX_INPUTS as u64);
+            let packet = Packet {
+                player: self.local,
+                ack: self.received[peer],
+                checksum: self.latest,
+                start,
+                inputs: (start..end)
+                    .map(|frame| self.inputs[frame as usize][self.local].unwrap())
+                    .collect(),
+            };
+            self.transport.send(peer, &packet.encode())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_round_trip() {
+        let packet = Packet {
+            player: 2,
+            ack: 40,
+            checksum: Some((30, 0xdead_beef)),
+            start: 38,
+            inputs: vec![
+                ShipInput {
+                    thrust: true,
+                    ..ShipInput::default()
+                },
+                ShipInput::default(),
+            ],
+        };
+        let bytes = packet.encode();
+        assert_eq!(Packet::decode(&bytes), Some(packet.clone()));
+        assert_eq!(Packet::decode(&bytes[..bytes.len() - 1]), None);
+        let quiet = Packet {
+            checksum: None,
+            inputs: Vec::new(),
+            ..packet
+        };
+        assert_eq!(Packet::decode(&quiet.encode()), Some(quiet));
+    }
+}
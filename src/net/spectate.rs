@@ -0,0 +1,262 @@
+//! Watching a game over TCP. The player's instance streams every frame's
+//! inputs, with a snapshot of the whole world as a keyframe now and then;
+//! spectators start from the latest keyframe, simulate the inputs since and
+//! then follow a little behind.
+
+use crate::input::ShipInput;
+use crate::snapshot;
+use crate::world::World;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 4] = b"FXS1";
+// Frames between keyframes; a late spectator catches up through at most this
+// many.
+const KEYFRAME_EVERY: u64 = 600;
+// Spectators that fall this far behind on reading are dropped.
+const MAX_PENDING: usize = 4 << 20;
+// Spectators this many frames behind where they should be skip ahead.
+const CATCH_UP: u64 = 60;
+const KEYFRAME: u8 = b'K';
+const INPUTS: u8 = b'I';
+
+/// The player's side: accepts spectators on a TCP port and sends them the
+/// game as it is played.
+pub struct Broadcaster {
+    listener: TcpListener,
+    spectators: Vec<Outgoing>,
+    // The latest keyframe and every message after it, the start of a late
+    // spectator's stream.
+    backlog: Vec<u8>,
+}
+struct Outgoing {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+impl Broadcaster {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Broadcaster> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcaster {
+            listener,
+            spectators: Vec::new(),
+            backlog: Vec::new(),
+        })
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+    /// Sends the frame `world` is about to play with `inputs`. Call it
+    /// before every `World::step`.
+    pub fn frame(&mut self, world: &World, inputs: &[ShipInput]) -> io::Result<()> {
+        self.accept()?;
+        if self.backlog.is_empty() || world.frame.is_multiple_of(KEYFRAME_EVERY) {
+            self.backlog.clear();
+            let keyframe = message(KEYFRAME, &snapshot::encode(world));
+            self.send(&keyframe);
+        }
+        let mut payload = world.frame.to_le_bytes().to_vec();
+        payload.extend(inputs.iter().map(ShipInput::bits));
+        self.send(&message(INPUTS, &payload));
+        self.flush();
+        Ok(())
+    }
+    /// Gives spectators up to `timeout` to receive the rest of the game,
+    /// then hangs up on them.
+    pub fn finish(mut self, timeout: Duration) {
+        let started = Instant::now();
+        while !self.spectators.is_empty() && started.elapsed() < timeout {
+            self.flush();
+            self.spectators
+                .retain(|spectator| !spectator.pending.is_empty());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+    fn accept(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    let mut pending = MAGIC.to_vec();
+                    pending.extend_from_slice(&self.backlog);
+                    self.spectators.push(Outgoing { stream, pending });
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    fn send(&mut self, message: &[u8]) {
+        self.backlog.extend_from_slice(message);
+        for spectator in &mut self.spectators {
+            spectator.pending.extend_from_slice(message);
+        }
+    }
+    // Writes what each spectator will take without blocking; the ones that
+    // hung up or stopped reading are dropped.
+    fn flush(&mut self) {
+        self.spectators.retain_mut(|spectator| {
+            while !spectator.pending.is_empty() {
+                match spectator.stream.write(&spectator.pending) {
+                    Ok(0) => return false,
+                    Ok(written) => drop(spectator.pending.drain(..written)),
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(_) => return false,
+                }
+            }
+            spectator.pending.len() <= MAX_PENDING
+        });
+    }
+}
+
+// A message on the stream: its kind, its length and then the payload.
+fn message(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![kind];
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+enum Message {
+    Keyframe(Box<World>),
+    Inputs(u64, Vec<ShipInput>),
+}
+
+/// The watching side: a copy of someone else's game, kept `delay` frames
+/// behind the newest frame that has arrived so it plays smoothly.
+pub struct Spectator {
+    stream: TcpStream,
+    delay: u64,
+    // Bytes read that do not make a whole message yet.
+    buffer: Vec<u8>,
+    started: bool,
+    queue: VecDeque<Message>,
+    // The frame after the newest inputs received.
+    latest: u64,
+    world: Option<World>,
+    closed: bool,
+}
+impl Spectator {
+    pub fn connect(address: impl ToSocketAddrs, delay: u64) -> io::Result<Spectator> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Spectator {
+            stream,
+            delay,
+            buffer: Vec::new(),
+            started: false,
+            queue: VecDeque::new(),
+            latest: 0,
+            world: None,
+            closed: false,
+        })
+    }
+    /// The game as far as it has been shown; `None` until the first
+    /// keyframe arrives.
+    pub fn world(&self) -> Option<&World> {
+        self.world.as_ref()
+    }
+    /// True once the player has hung up and every frame they sent is shown.
+    pub fn ended(&self) -> bool {
+        self.closed && self.queue.is_empty()
+    }
+    /// Reads what has arrived and shows the next frame, or several to catch
+    /// up after joining or a hiccup. Call it once per frame.
+    pub fn tick(&mut self) -> io::Result<()> {
+        self.receive()?;
+        // Once the player is gone there is nothing to wait for.
+        let target = if self.closed {
+            self.latest
+        } else {
+            self.latest.saturating_sub(self.delay)
+        };
+        let mut hurry = None;
+        let mut stepped = false;
+        while let Some(message) = self.queue.pop_front() {
+            match message {
+                Message::Keyframe(world) => self.world = Some(*world),
+                Message::Inputs(frame, inputs) => {
+                    let Some(world) = &mut self.world else {
+                        // Frames from before the first keyframe.
+                        continue;
+                    };
+                    let hurry = *hurry.get_or_insert(world.frame + CATCH_UP < target);
+                    if frame >= target || (!hurry && stepped) {
+                        self.queue.push_front(Message::Inputs(frame, inputs));
+                        break;
+                    }
+                    if frame == world.frame {
+                        world.step(&inputs);
+                        stepped = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    fn receive(&mut self) -> io::Result<()> {
+        let mut chunk = [0; 16384];
+        while !self.closed {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(len) => self.buffer.extend_from_slice(&chunk[..len]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what);
+        if !self.started {
+            if self.buffer.len() < MAGIC.len() {
+                return Ok(());
+            }
+            if !self.buffer.starts_with(MAGIC) {
+                return Err(invalid("not a foxtroids broadcast"));
+            }
+            self.buffer.drain(..MAGIC.len());
+            self.started = true;
+        }
+        let mut rest = &self.buffer[..];
+        while let Some((&kind, after)) = rest.split_first() {
+            let Some((len, after)) = after.split_first_chunk() else {
+                break;
+            };
+            let len = u32::from_le_bytes(*len) as usize;
+            // Nothing the broadcaster sends is bigger than it would queue.
+            if len > MAX_PENDING {
+                return Err(invalid("broadcast message too long"));
+            }
+            let Some(payload) = after.get(..len) else {
+                break;
+            };
+            rest = &after[len..];
+            let message = match kind {
+                KEYFRAME => {
+                    snapshot::decode(payload).map(|world| Message::Keyframe(Box::new(world)))
+                }
+                INPUTS => payload.split_first_chunk().and_then(|(frame, bits)| {
+                    let frame = u64::from_le_bytes(*frame);
+                    self.latest = frame.checked_add(1)?;
+                    Some(Message::Inputs(
+                        frame,
+                        bits.iter().map(|b| ShipInput::from_bits(*b)).collect(),
+                    ))
+                }),
+                _ => None,
+            };
+            self.queue
+                .push_back(message.ok_or_else(|| invalid("broken broadcast message"))?);
+        }
+        let used = self.buffer.len() - rest.len();
+        self.buffer.drain(..used);
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! How netplay packets travel: UDP sockets, optionally through a simulated
+//! bad network for testing on one machine.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// Larger than any packet `NetSession` sends.
+const MAX_PACKET: usize = 1024;
+
+/// Sends packets to the other players and collects theirs, without blocking.
+/// Packets may be lost, duplicated or reordered on the way.
+pub trait Transport {
+    /// Sends `packet` towards player `peer`.
+    fn send(&mut self, peer: usize, packet: &[u8]) -> io::Result<()>;
+    /// The next packet that has arrived, if any.
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// One UDP socket talking to every other player.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peers: Vec<SocketAddr>,
+}
+impl UdpTransport {
+    /// Binds player `local`'s address in `peers`, the addresses of all
+    /// players in player order.
+    pub fn bind(local: usize, peers: Vec<SocketAddr>) -> io::Result<UdpTransport> {
+        UdpTransport::new(UdpSocket::bind(peers[local])?, peers)
+    }
+    /// Uses an already bound socket, e.g. one on port 0 in tests.
+    pub fn new(socket: UdpSocket, peers: Vec<SocketAddr>) -> io::Result<UdpTransport> {
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket, peers })
+    }
+}
+impl Transport for UdpTransport {
+    fn send(&mut self, peer: usize, packet: &[u8]) -> io::Result<()> {
+        match self.socket.send_to(packet, self.peers[peer]) {
+            // A peer that is not up yet is no reason to stop.
+            Err(error) if is_transient(&error) => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) if self.peers.contains(&from) => {
+                    return Ok(Some(buffer[..len].to_vec()))
+                }
+                Ok(_) => continue,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(error) if is_transient(&error) => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+// Errors left over from an ICMP "port unreachable" while a peer is down.
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// A bad network to simulate: each packet is lost with probability `loss`,
+/// otherwise delayed by `latency` give or take up to `jitter`, which also
+/// reorders packets.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Conditions {
+    pub loss: f64,
+    pub latency: Duration,
+    pub jitter: Duration,
+}
+/// Parses `loss,latency,jitter` with the times in milliseconds, e.g.
+/// `0.1,50,20`.
+impl FromStr for Conditions {
+    type Err = String;
+    fn from_str(text: &str) -> Result<Conditions, String> {
+        let usage = || format!("expected loss,latency,jitter, not {}", text);
+        let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+        let [loss, latency, jitter] = parts[..] else {
+            return Err(usage());
+        };
+        let millis = |text: &str| text.parse().map(Duration::from_millis).ok();
+        Ok(Conditions {
+            loss: loss
+                .parse()
+                .ok()
+                .filter(|loss| (0.0..=1.0).contains(loss))
+                .ok_or_else(usage)?,
+            latency: millis(latency).ok_or_else(usage)?,
+            jitter: millis(jitter).ok_or_else(usage)?,
+        })
+    }
+}
+
+/// Wraps another transport in simulated `Conditions`. Delayed packets wait
+/// on the sending side and go out from `recv`, which a session calls every
+/// tick.
+pub struct LossyTransport<T> {
+    inner: T,
+    conditions: Conditions,
+    rng: StdRng,
+    // Packets held back: when they are due, to whom, and what.
+    queue: Vec<(Instant, usize, Vec<u8>)>,
+}
+impl<T: Transport> LossyTransport<T> {
+    pub fn new(inner: T, conditions: Conditions, seed: u64) -> LossyTransport<T> {
+        LossyTransport {
+            inner,
+            conditions,
+            rng: StdRng::seed_from_u64(seed),
+            queue: Vec::new(),
+        }
+    }
+}
+impl<T: Transport> Transport for LossyTransport<T> {
+    fn send(&mut self, peer: usize, packet: &[u8]) -> io::Result<()> {
+        if self.rng.gen_bool(self.conditions.loss) {
+            return Ok(());
+        }
+        let jitter = self.conditions.jitter.as_secs_f64();
+        let delay = self.conditions.latency.as_secs_f64() + self.rng.gen_range(-jitter..=jitter);
+        let due = Instant::now() + Duration::from_secs_f64(delay.max(0.0));
+        self.queue.push((due, peer, packet.to_vec()));
+        Ok(())
+    }
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let now = Instant::now();
+        let mut i = 0;
+        while i < self.queue.len() {
+            if self.queue[i].0 <= now {
+                let (_, peer, packet) = self.queue.swap_remove(i);
+                self.inner.send(peer, &packet)?;
+            } else {
+                i += 1;
+            }
+        }
+        self.inner.recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_parse() {
+        let conditions: Conditions = "0.25, 40, 10".parse().unwrap();
+        assert_eq!(conditions.loss, 0.25);
+        assert_eq!(conditions.latency, Duration::from_millis(40));
+        assert_eq!(conditions.jitter, Duration::from_millis(10));
+        assert!("2,40,10".parse::<Conditions>().is_err());
+        assert!("0.1,40".parse::<Conditions>().is_err());
+    }
+}
@@ -0,0 +1,447 @@
+//! Pilots grown rather than written: small neural networks of a fixed shape
+//! that turn `env::features` into buttons, evolved with a genetic algorithm
+//! by playing seeded games headless. `foxtroids-train` runs the evolution
+//! and the game flies the result with `--pilot FILE`.
+
+use crate::ai::Pilot;
+use crate::control::{commands, Idle, Observation, ShipController};
+use crate::env::{feature_count, features};
+use crate::input::ShipInput;
+use crate::settings::Rules;
+use crate::world::World;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::f64::consts::TAU;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const GENOME_HEADER: &str = "foxtroids genome 1";
+const POPULATION_HEADER: &str = "foxtroids population 1";
+// Fitness for each second alive and for hitting with every shot, next to
+// one for each point scored.
+const PER_SECOND: f64 = 5.0;
+const PER_ACCURACY: f64 = 100.0;
+// Share of each generation carried over unchanged, best first.
+const ELITE: f64 = 0.1;
+// Genomes drawn to pick each parent; the fittest of them wins.
+const TOURNAMENT: usize = 3;
+// Chance that a weight changes in a child, and the standard deviation of
+// the change.
+const MUTATION_RATE: f64 = 0.3;
+const MUTATION_STRENGTH: f64 = 0.1;
+
+/// The weights of a fully connected network, tanh between the layers. It
+/// sees `features(observation, nearest, rays)` and holds each of the six
+/// buttons, in `ShipInput::buttons` order, while its output is positive.
+///
+/// Stored as text: a header, `nearest`, `rays` and `layers` lines, then
+/// `weights` and every weight, each layer's biases after its inputs'.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Genome {
+    pub nearest: usize,
+    pub rays: usize,
+    /// Neurons per layer, inputs first and the six buttons last.
+    pub layers: Vec<usize>,
+    pub weights: Vec<f32>,
+}
+impl Genome {
+    /// A network with `hidden` neurons per hidden layer and small random
+    /// weights.
+    pub fn random(nearest: usize, rays: usize, hidden: &[usize], rng: &mut impl Rng) -> Genome {
+        let mut layers = vec![feature_count(nearest, rays)];
+        layers.extend(hidden);
+        layers.push(6);
+        let mut weights = Vec::new();
+        for pair in layers.windows(2) {
+            let spread = 1.0 / ((pair[0] + 1) as f32).sqrt();
+            weights.extend((0..(pair[0] + 1) * pair[1]).map(|_| rng.gen_range(-spread..=spread)));
+        }
+        Genome {
+            nearest,
+            rays,
+            layers,
+            weights,
+        }
+    }
+    /// The network's outputs for `inputs`.
+    pub fn run(&self, inputs: &[f32]) -> Vec<f32> {
+        let mut values = inputs.to_vec();
+        let mut weights = &self.weights[..];
+        for (i, pair) in self.layers.windows(2).enumerate() {
+            let last = i + 2 == self.layers.len();
+            values = (0..pair[1])
+                .map(|_| {
+                    let (neuron, rest) = weights.split_at(pair[0] + 1);
+                    weights = rest;
+                    let sum: f32 = neuron.iter().zip(&values).map(|(w, v)| w * v).sum();
+                    let sum = sum + neuron[pair[0]];
+                    if last {
+                        sum
+                    } else {
+                        sum.tanh()
+                    }
+                })
+                .collect();
+        }
+        values
+    }
+    /// A child taking each weight from either parent, which must have the
+    /// same shape.
+    pub fn cross(&self, other: &Genome, rng: &mut impl Rng) -> Genome {
+        let weights = self
+            .weights
+            .iter()
+            .zip(&other.weights)
+            .map(|(a, b)| if rng.gen() { *a } else { *b })
+            .collect();
+        Genome {
+            weights,
+            ..self.clone()
+        }
+    }
+    /// Nudges some of the weights by normally distributed amounts.
+    pub fn mutate(&mut self, rng: &mut impl Rng) {
+        for weight in &mut self.weights {
+            if rng.gen_bool(MUTATION_RATE) {
+                *weight += (normal(rng) * MUTATION_STRENGTH) as f32;
+            }
+        }
+    }
+    pub fn parse(text: &str) -> Result<Genome, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        if lines.next() != Some(GENOME_HEADER) {
+            return Err(format!("missing \"{}\" header", GENOME_HEADER));
+        }
+        let mut value = |key: &str| {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(key))
+                .map(str::trim)
+                .ok_or_else(|| format!("missing {} line", key))
+        };
+        let number = |text: &str| text.parse().map_err(|_| format!("bad number: {}", text));
+        let nearest = number(value("nearest")?)?;
+        let rays = number(value("rays")?)?;
+        let layers = value("layers")?
+            .split_whitespace()
+            .map(number)
+            .collect::<Result<Vec<usize>, _>>()?;
+        value("weights")?;
+        let weights = lines
+            .flat_map(str::split_whitespace)
+            .map(|weight| {
+                weight
+                    .parse()
+                    .map_err(|_| format!("bad weight: {}", weight))
+            })
+            .collect::<Result<Vec<f32>, _>>()?;
+        let expected: usize = layers.windows(2).map(|pair| (pair[0] + 1) * pair[1]).sum();
+        if layers.len() < 2
+            || layers[0] != feature_count(nearest, rays)
+            || layers.last() != Some(&6)
+            || weights.len() != expected
+        {
+            return Err("layers and weights do not fit together".into());
+        }
+        Ok(Genome {
+            nearest,
+            rays,
+            layers,
+            weights,
+        })
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Genome> {
+        Genome::parse(&std::fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+impl fmt::Display for Genome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", GENOME_HEADER)?;
+        writeln!(f, "nearest {}", self.nearest)?;
+        writeln!(f, "rays {}", self.rays)?;
+        let layers: Vec<String> = self.layers.iter().map(usize::to_string).collect();
+        writeln!(f, "layers {}", layers.join(" "))?;
+        writeln!(f, "weights")?;
+        for line in self.weights.chunks(16) {
+            let line: Vec<String> = line.iter().map(f32::to_string).collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+// A standard normal sample, by the Box-Muller transform.
+fn normal(rng: &mut impl Rng) -> f64 {
+    let (u, v): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
+
+/// Flies a ship with a genome's network.
+pub struct NeuralPilot(pub Genome);
+impl ShipController for NeuralPilot {
+    fn command(&mut self, observation: &Observation) -> ShipInput {
+        let genome = &self.0;
+        let outputs = genome.run(&features(observation, genome.nearest, genome.rays));
+        ShipInput::from_buttons(std::array::from_fn(|i| outputs[i] > 0.0))
+    }
+}
+
+/// How a genome did over some games, summed.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Fitness {
+    pub games: u32,
+    pub points: u32,
+    /// Frames the ship was alive.
+    pub frames: u64,
+    pub shots: u32,
+    /// Frames a shot scored in.
+    pub hits: u32,
+}
+impl Fitness {
+    /// Hits per shot.
+    pub fn accuracy(&self) -> f64 {
+        self.hits as f64 / self.shots.max(1) as f64
+    }
+    /// What the evolution maximises: points and seconds alive per game, and
+    /// accuracy.
+    pub fn value(&self) -> f64 {
+        let games = self.games.max(1) as f64;
+        let seconds = self.frames as f64 / 60.0;
+        (self.points as f64 + seconds * PER_SECOND) / games + self.accuracy() * PER_ACCURACY
+    }
+}
+
+/// The games every genome of a generation plays: one for each seed, cut
+/// short after `frames`. Any other ships are flown by the built-in pilot.
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub rules: Rules,
+    pub seeds: Vec<u64>,
+    pub frames: u64,
+}
+impl Trial {
+    /// Plays every game with `pilot` flying ship 0.
+    pub fn play(&self, pilot: &mut dyn ShipController) -> Fitness {
+        let mut fitness = Fitness::default();
+        for seed in &self.seeds {
+            let mut world = World::with_rules(self.rules.clone(), *seed);
+            let mut others: Vec<Box<dyn ShipController>> = vec![Box::new(Idle)];
+            for _ in 1..self.rules.players {
+                others.push(Box::new(Pilot));
+            }
+            fitness.games += 1;
+            while world.frame < self.frames {
+                let ship = &world.ships[0];
+                if !ship.alive && ship.respawn == 0 {
+                    break;
+                }
+                let mut inputs = commands(&world, &mut others);
+                inputs[0] = pilot.command(&Observation {
+                    world: &world,
+                    ship: 0,
+                });
+                let before = ship.score;
+                if ship.alive {
+                    fitness.frames += 1;
+                    fitness.shots += (inputs[0].fire && ship.fire_delay == 0) as u32;
+                }
+                world.step(&inputs);
+                let scored = world.ships[0].score - before;
+                fitness.points += scored;
+                fitness.hits += (scored > 0) as u32;
+            }
+        }
+        fitness
+    }
+    /// Plays every genome on `threads` threads at once.
+    pub fn rank(&self, genomes: &[Genome], threads: usize) -> Vec<Fitness> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![Fitness::default(); genomes.len()]);
+        std::thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(genome) = genomes.get(i) else {
+                        break;
+                    };
+                    let fitness = self.play(&mut NeuralPilot(genome.clone()));
+                    results.lock().unwrap()[i] = fitness;
+                });
+            }
+        });
+        results.into_inner().unwrap()
+    }
+}
+
+/// A generation of genomes and the best one seen so far. Saved as text: a
+/// header, `seed` and `generation` lines, then `best` and its fitness value
+/// followed by that genome if there is one, and every genome of the
+/// generation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Population {
+    /// Drives the evolution, together with the generation.
+    pub seed: u64,
+    pub generation: u32,
+    pub genomes: Vec<Genome>,
+    pub best: Option<(f64, Genome)>,
+}
+impl Population {
+    /// `size` random genomes of the same shape.
+    pub fn new(
+        size: usize,
+        seed: u64,
+        nearest: usize,
+        rays: usize,
+        hidden: &[usize],
+    ) -> Population {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        Population {
+            seed,
+            generation: 0,
+            genomes: (0..size)
+                .map(|_| Genome::random(nearest, rays, hidden, &mut rng))
+                .collect(),
+            best: None,
+        }
+    }
+    /// Replaces the genomes by the next generation, bred from the fittest
+    /// by the fitness values in the same order. The best of the generation
+    /// becomes `best` if it did better.
+    pub fn breed(&mut self, fitness: &[f64]) {
+        let mut ranked: Vec<(f64, &Genome)> = fitness.iter().copied().zip(&self.genomes).collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        if let Some((value, genome)) = ranked.first() {
+            if self.best.as_ref().is_none_or(|best| *value > best.0) {
+                self.best = Some((*value, (*genome).clone()));
+            }
+        }
+        let mut rng = ChaCha12Rng::seed_from_u64(self.seed);
+        rng.set_stream(self.generation as u64 + 1);
+        let elite = ((ranked.len() as f64 * ELITE).ceil() as usize).min(ranked.len());
+        let pick = |rng: &mut ChaCha12Rng| {
+            (0..TOURNAMENT)
+                .map(|_| ranked[rng.gen_range(0..ranked.len())])
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap()
+                .1
+        };
+        let mut next: Vec<Genome> = ranked[..elite].iter().map(|(_, g)| (*g).clone()).collect();
+        while next.len() < ranked.len() {
+            let (a, b) = (pick(&mut rng), pick(&mut rng));
+            let mut child = a.cross(b, &mut rng);
+            child.mutate(&mut rng);
+            next.push(child);
+        }
+        self.genomes = next;
+        self.generation += 1;
+    }
+    pub fn parse(text: &str) -> Result<Population, String> {
+        let mut blocks = text.split(GENOME_HEADER);
+        let mut lines = blocks
+            .next()
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        if lines.next() != Some(POPULATION_HEADER) {
+            return Err(format!("missing \"{}\" header", POPULATION_HEADER));
+        }
+        let mut value = |key: &str| {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(key))
+                .and_then(|value| value.trim().parse().ok())
+                .ok_or_else(|| format!("missing {} line", key))
+        };
+        let seed = value("seed")?;
+        let generation = value("generation")? as u32;
+        let best = lines.next().map(|line| {
+            line.strip_prefix("best ")
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .ok_or_else(|| format!("bad line: {}", line))
+        });
+        let mut genomes = blocks
+            .map(|block| Genome::parse(&format!("{}{}", GENOME_HEADER, block)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let best = match best.transpose()? {
+            Some(value) if !genomes.is_empty() => Some((value, genomes.remove(0))),
+            Some(_) => return Err("best without a genome".into()),
+            None => None,
+        };
+        Ok(Population {
+            seed,
+            generation,
+            genomes,
+            best,
+        })
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Population> {
+        Population::parse(&std::fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+    /// Writes next to `path` first and then moves it over, so an
+    /// interrupted save leaves the last checkpoint whole.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, self.to_string())?;
+        std::fs::rename(partial, path)
+    }
+}
+impl fmt::Display for Population {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", POPULATION_HEADER)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "generation {}", self.generation)?;
+        if let Some((value, genome)) = &self.best {
+            writeln!(f, "best {}", value)?;
+            write!(f, "{}", genome)?;
+        }
+        for genome in &self.genomes {
+            write!(f, "{}", genome)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn populations_evolve_and_round_trip() {
+        let mut population = Population::new(6, 3, 2, 4, &[5]);
+        let trial = Trial {
+            rules: Rules::default(),
+            seeds: vec![1, 2],
+            frames: 600,
+        };
+        let ranked = trial.rank(&population.genomes, 3);
+        assert_eq!(ranked, trial.rank(&population.genomes, 1));
+        assert!(ranked.iter().all(|fitness| fitness.games == 2));
+        let values: Vec<f64> = ranked.iter().map(Fitness::value).collect();
+        let best = values.iter().copied().fold(f64::MIN, f64::max);
+        population.breed(&values);
+        assert_eq!(population.generation, 1);
+        assert_eq!(population.genomes.len(), 6);
+        assert_eq!(population.best.as_ref().unwrap().0, best);
+        // The best genome comes first in the next generation, unchanged.
+        assert_eq!(population.genomes[0], population.best.as_ref().unwrap().1);
+        let text = population.to_string();
+        assert_eq!(Population::parse(&text).unwrap(), population);
+        let genome = &population.genomes[1];
+        assert_eq!(&Genome::parse(&genome.to_string()).unwrap(), genome);
+        let resized = genome.to_string().replace("layers 34 5 6", "layers 34 6");
+        assert!(Genome::parse(&resized).is_err());
+    }
+}
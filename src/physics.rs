@@ -0,0 +1,133 @@
+//! Collision detection and its broad phase.
+
+use crate::arena::{Arena, Handle};
+use crate::math::Vec2;
+use crate::shape::{split_asteroid, Shape};
+use rand::Rng;
+
+/// Uniform grid over the playfield used as a broad phase: bullets are binned
+/// by position so an asteroid only tests the bullets in cells it overlaps.
+#[derive(Clone)]
+pub struct SpatialGrid {
+    pub cell: f64,
+    pub cols: usize,
+    pub rows: usize,
+    cells: Vec<Vec<Handle>>,
+}
+impl SpatialGrid {
+    pub fn new(bounds: Vec2, cell: f64) -> SpatialGrid {
+        let cols = (bounds.x / cell).ceil().max(1.0) as usize;
+        let rows = (bounds.y / cell).ceil().max(1.0) as usize;
+        SpatialGrid {
+            cell,
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+        }
+    }
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+    fn cell_of(&self, pos: Vec2) -> (isize, isize) {
+        (
+            (pos.x / self.cell).floor() as isize,
+            (pos.y / self.cell).floor() as isize,
+        )
+    }
+    /// Bins `handle` at `pos`; points outside the grid are ignored.
+    pub fn insert(&mut self, handle: Handle, pos: Vec2) {
+        let (col, row) = self.cell_of(pos);
+        if (0..self.cols as isize).contains(&col) && (0..self.rows as isize).contains(&row) {
+            self.cells[row as usize * self.cols + col as usize].push(handle);
+        }
+    }
+    /// Every handle binned in a cell touched by the square around the circle.
+    pub fn query(&self, center: Vec2, radius: f64) -> Vec<Handle> {
+        let (min_col, min_row) = self.cell_of(center - Vec2::new(radius, radius));
+        let (max_col, max_row) = self.cell_of(center + Vec2::new(radius, radius));
+        let mut found = Vec::new();
+        for row in min_row.max(0)..=max_row.min(self.rows as isize - 1) {
+            for col in min_col.max(0)..=max_col.min(self.cols as isize - 1) {
+                found.extend_from_slice(&self.cells[row as usize * self.cols + col as usize]);
+            }
+        }
+        found
+    }
+    /// Column, row and entity count of every non-empty cell.
+    pub fn occupied(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(i, cell)| (i % self.cols, i / self.cols, cell.len()))
+    }
+}
+
+/// Whether `to_destroy` touches any shape in `tester`.
+pub fn destroy(tester: &Arena<Shape>, to_destroy: &Shape, bounds: Vec2) -> bool {
+    tester.values().any(|shape| {
+        // Bound shapes are also drawn across the edges, so they can hit there too.
+        let distance = if shape.bound {
+            shape.pos.wrapped_distance(to_destroy.pos, bounds)
+        } else {
+            (shape.pos - to_destroy.pos).length()
+        };
+        distance <= (shape.scale * 0.8) + (to_destroy.scale * 0.8)
+    })
+}
+/// Splits every shape in `tester` hit by something in `testee` and returns
+/// the owner of each hitting shape with the points it scored. Queues the
+/// split halves and despawns; the caller flushes. `grid` must hold the
+/// positions of everything in `testee`.
+pub fn collide(
+    tester: &mut Arena<Shape>,
+    testee: &mut Arena<Shape>,
+    grid: &SpatialGrid,
+    rng: &mut impl Rng,
+) -> Vec<(usize, u32)> {
+    let mut scored = Vec::new();
+    for i in tester.handles() {
+        let shape = tester.get(i).unwrap();
+        let hit = grid.query(shape.pos, shape.scale).into_iter().find(|j| {
+            !testee.despawning(*j)
+                && testee
+                    .get(*j)
+                    .is_some_and(|other| shape.contains(other.pos))
+        });
+        if let Some(j) = hit {
+            scored.push((testee.get(j).unwrap().owner, points(shape)));
+            for piece in split_asteroid(shape, rng) {
+                tester.spawn(piece);
+            }
+            tester.despawn(i);
+            testee.despawn(j);
+        }
+    }
+    scored
+}
+/// Score for destroying `shape`: bigger asteroids are worth more.
+pub fn points(shape: &Shape) -> u32 {
+    shape.scale as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_query_finds_neighbours_only() {
+        let mut arena = Arena::new();
+        let near = arena.insert(());
+        let far = arena.insert(());
+        let mut grid = SpatialGrid::new(Vec2::new(600.0, 600.0), 80.0);
+        grid.insert(near, Vec2::new(100.0, 100.0));
+        grid.insert(far, Vec2::new(500.0, 500.0));
+        grid.insert(far, Vec2::new(-5.0, 100.0));
+        assert_eq!(grid.query(Vec2::new(120.0, 90.0), 30.0), vec![near]);
+        assert_eq!(grid.occupied().count(), 2);
+        grid.clear();
+        assert!(grid.query(Vec2::new(100.0, 100.0), 600.0).is_empty());
+    }
+}
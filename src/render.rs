@@ -0,0 +1,161 @@
+//! Drawing backends. Game code draws through the `Renderer` trait so it never
+//! depends on a particular window system. The SDL canvas implementation lives
+//! in `sdl`; `software` draws into memory, `svg` into a vector document and
+//! `terminal` into text.
+
+use crate::font::text_lines;
+use crate::math::Vec2;
+use crate::settings::Scaling;
+
+pub mod monitor;
+pub mod software;
+pub mod svg;
+pub mod terminal;
+
+/// An RGBA colour; alpha is only used by filled rectangles.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+/// Something vector graphics can be drawn onto.
+pub trait Renderer {
+    /// Colour used by the drawing calls that follow.
+    fn set_color(&mut self, color: Color);
+    fn line(&mut self, a: Vec2, b: Vec2);
+    fn point(&mut self, p: Vec2);
+    /// Filled rectangle, blended when the colour is translucent.
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2);
+    /// Fills the whole target with `color`.
+    fn clear(&mut self, color: Color);
+    /// Shows what has been drawn since the last `present`.
+    fn present(&mut self);
+
+    /// Open line through `points`.
+    fn polyline(&mut self, points: &[Vec2]) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1]);
+        }
+    }
+    /// Closed outline through `points`.
+    fn polygon(&mut self, points: &[Vec2]) {
+        for i in 0..points.len() {
+            self.line(points[i], points[(i + 1) % points.len()]);
+        }
+    }
+    /// `text` in the stroke font, `size` pixels tall, top left at `origin`.
+    fn text(&mut self, text: &str, origin: Vec2, size: f64) {
+        for (a, b) in text_lines(text, origin, size) {
+            self.line(a, b);
+        }
+    }
+}
+
+/// Where a picture of a fixed logical size lands in an output of another
+/// size: scaled by `scale` and centred, leaving bars around it.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Viewport {
+    pub scale: f64,
+    /// Top left corner in output pixels.
+    pub offset: Vec2,
+    /// Size in output pixels.
+    pub size: Vec2,
+}
+impl Viewport {
+    pub fn fit(logical: Vec2, output: Vec2, scaling: Scaling) -> Viewport {
+        let fit = (output.x / logical.x).min(output.y / logical.y);
+        let scale = match scaling {
+            Scaling::Stretched => fit,
+            Scaling::PixelExact if fit >= 1.0 => fit.floor(),
+            Scaling::PixelExact => 1.0 / (1.0 / fit).ceil(),
+        };
+        let size = logical * scale;
+        let offset = (output - size) / 2.0;
+        Viewport {
+            scale,
+            offset: Vec2::new(offset.x.floor(), offset.y.floor()),
+            size,
+        }
+    }
+    /// Draws logical coordinates through to `inner`.
+    pub fn transform<'a>(&self, inner: &'a mut dyn Renderer) -> Transform<'a> {
+        Transform {
+            inner,
+            scale: self.scale,
+            offset: self.offset,
+        }
+    }
+}
+
+/// Draws through to another renderer with every point scaled about the
+/// origin and then moved by `offset`.
+pub struct Transform<'a> {
+    pub inner: &'a mut dyn Renderer,
+    pub scale: f64,
+    pub offset: Vec2,
+}
+impl Transform<'_> {
+    fn apply(&self, p: Vec2) -> Vec2 {
+        p * self.scale + self.offset
+    }
+}
+impl Renderer for Transform<'_> {
+    fn set_color(&mut self, color: Color) {
+        self.inner.set_color(color);
+    }
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        let (a, b) = (self.apply(a), self.apply(b));
+        self.inner.line(a, b);
+    }
+    fn point(&mut self, p: Vec2) {
+        let p = self.apply(p);
+        self.inner.point(p);
+    }
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2) {
+        let pos = self.apply(pos);
+        self.inner.fill_rect(pos, size * self.scale);
+    }
+    fn clear(&mut self, color: Color) {
+        self.inner.clear(color);
+    }
+    fn present(&mut self) {
+        self.inner.present();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewports_letterbox() {
+        let logical = Vec2::new(600.0, 600.0);
+        let wide = Viewport::fit(logical, Vec2::new(1920.0, 1080.0), Scaling::Stretched);
+        assert_eq!(wide.scale, 1.8);
+        assert_eq!(wide.offset, Vec2::new(420.0, 0.0));
+        assert_eq!(wide.size, Vec2::new(1080.0, 1080.0));
+        let exact = Viewport::fit(logical, Vec2::new(1920.0, 1080.0), Scaling::PixelExact);
+        assert_eq!(exact.scale, 1.0);
+        assert_eq!(exact.offset, Vec2::new(660.0, 240.0));
+        let hidpi = Viewport::fit(logical, Vec2::new(1300.0, 1250.0), Scaling::PixelExact);
+        assert_eq!(hidpi.scale, 2.0);
+        assert_eq!(hidpi.offset, Vec2::new(50.0, 25.0));
+        let small = Viewport::fit(logical, Vec2::new(400.0, 500.0), Scaling::PixelExact);
+        assert_eq!(small.scale, 0.5);
+        assert_eq!(small.size, Vec2::new(300.0, 300.0));
+    }
+}
@@ -0,0 +1,305 @@
+//! A software "vector monitor": wide antialiased beams, glow, phosphor
+//! persistence, beam jitter, scanlines and tube curvature, all on the CPU.
+
+use super::software::Framebuffer;
+use super::{Color, Renderer};
+use crate::math::Vec2;
+use crate::settings::MonitorSettings;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Share of the previous frame's phosphor glow left after one frame.
+const DECAY: f32 = 0.6;
+// Radius of the glow blur in pixels, and how strongly it is added back.
+const GLOW_RADIUS: usize = 5;
+const GLOW_STRENGTH: f32 = 1.4;
+// Largest beam offset from the ideal line, in pixels.
+const JITTER: f64 = 0.35;
+// Brightness of every other row with scanlines on.
+const SCANLINE: f32 = 0.65;
+// Barrel distortion: how far the corners are pulled in.
+const CURVATURE: f64 = 0.08;
+
+/// A `Renderer` that collects beam energy per pixel and turns it into a
+/// picture on `present`, which is then read with `frame`.
+pub struct VectorMonitor {
+    pub settings: MonitorSettings,
+    width: usize,
+    height: usize,
+    // Energy drawn this frame, per pixel, in linear 0-1 RGB; can exceed 1.
+    beam: Vec<[f32; 3]>,
+    // What the phosphor still shows, for persistence.
+    phosphor: Vec<[f32; 3]>,
+    background: Color,
+    color: Color,
+    rng: StdRng,
+    frame: Framebuffer,
+}
+impl VectorMonitor {
+    pub fn new(width: usize, height: usize, settings: MonitorSettings) -> VectorMonitor {
+        VectorMonitor {
+            settings,
+            width,
+            height,
+            beam: vec![[0.0; 3]; width * height],
+            phosphor: vec![[0.0; 3]; width * height],
+            background: Color::BLACK,
+            color: Color::WHITE,
+            rng: StdRng::seed_from_u64(0),
+            frame: Framebuffer::new(width, height),
+        }
+    }
+    /// The picture made by the last `present`.
+    pub fn frame(&self) -> &Framebuffer {
+        &self.frame
+    }
+    fn jitter(&mut self, p: Vec2) -> Vec2 {
+        if !self.settings.jitter {
+            return p;
+        }
+        p + Vec2::new(
+            self.rng.gen_range(-JITTER..=JITTER),
+            self.rng.gen_range(-JITTER..=JITTER),
+        )
+    }
+    // Adds the beam along a segment. A pixel's coverage falls off linearly
+    // over the last pixel of the beam's width.
+    fn beam(&mut self, a: Vec2, b: Vec2) {
+        // Pixel centres sit at half coordinates.
+        let (a, b) = (a + Vec2::new(0.5, 0.5), b + Vec2::new(0.5, 0.5));
+        let radius = self.settings.thickness / 2.0;
+        let reach = radius + 0.5;
+        let min = Vec2::new(a.x.min(b.x), a.y.min(b.y)) - Vec2::new(reach, reach);
+        let max = Vec2::new(a.x.max(b.x), a.y.max(b.y)) + Vec2::new(reach, reach);
+        let energy = [self.color.r, self.color.g, self.color.b].map(|c| c as f32 / 255.0);
+        let d = b - a;
+        let length_squared = d.length_squared();
+        for y in (min.y.floor().max(0.0) as usize)..(max.y.ceil().min(self.height as f64) as usize)
+        {
+            for x in
+                (min.x.floor().max(0.0) as usize)..(max.x.ceil().min(self.width as f64) as usize)
+            {
+                let p = Vec2::new(x as f64 + 0.5, y as f64 + 0.5);
+                let t = if length_squared == 0.0 {
+                    0.0
+                } else {
+                    ((p - a).dot(d) / length_squared).clamp(0.0, 1.0)
+                };
+                let distance = (p - a.lerp(b, t)).length();
+                let coverage = (reach - distance).clamp(0.0, 1.0) as f32;
+                if coverage > 0.0 {
+                    let pixel = &mut self.beam[y * self.width + x];
+                    for c in 0..3 {
+                        pixel[c] += energy[c] * coverage;
+                    }
+                }
+            }
+        }
+    }
+    // Separable box blur run twice, close enough to a gaussian for a glow.
+    fn blur(&self, image: &[[f32; 3]]) -> Vec<[f32; 3]> {
+        let mut image = image.to_vec();
+        for _ in 0..2 {
+            image = blur_pass(&image, self.width, self.height, 1, self.width);
+            image = blur_pass(&image, self.height, self.width, self.width, 1);
+        }
+        image
+    }
+    // Where each output pixel reads from on a curved tube, if anywhere.
+    fn curve(&self, x: usize, y: usize) -> Option<usize> {
+        let size = Vec2::new(self.width as f64, self.height as f64);
+        let uv = Vec2::new(x as f64 + 0.5, y as f64 + 0.5) * 2.0;
+        let uv = Vec2::new(uv.x / size.x - 1.0, uv.y / size.y - 1.0);
+        let uv = uv * (1.0 + CURVATURE * uv.length_squared());
+        let source = Vec2::new((uv.x + 1.0) * size.x, (uv.y + 1.0) * size.y) / 2.0;
+        if source.x < 0.0 || source.y < 0.0 || source.x >= size.x || source.y >= size.y {
+            return None;
+        }
+        Some(source.y as usize * self.width + source.x as usize)
+    }
+}
+
+// One direction of a box blur over `lines` lines of `length` pixels each;
+// `step` moves along a line and `stride` to the next one.
+fn blur_pass(
+    image: &[[f32; 3]],
+    length: usize,
+    lines: usize,
+    stride: usize,
+    step: usize,
+) -> Vec<[f32; 3]> {
+    let mut out = vec![[0.0; 3]; image.len()];
+    let r = GLOW_RADIUS as isize;
+    let norm = 1.0 / (2 * r + 1) as f32;
+    for line in 0..lines {
+        let at = |i: isize| image[line * stride + i.clamp(0, length as isize - 1) as usize * step];
+        let mut sum = [0.0f32; 3];
+        for i in -r..=r {
+            for (sum, value) in sum.iter_mut().zip(at(i)) {
+                *sum += value;
+            }
+        }
+        for i in 0..length as isize {
+            out[line * stride + i as usize * step] = sum.map(|s| s * norm);
+            let (add, remove) = (at(i + r + 1), at(i - r));
+            for c in 0..3 {
+                sum[c] += add[c] - remove[c];
+            }
+        }
+    }
+    out
+}
+
+impl Renderer for VectorMonitor {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        let (a, b) = (self.jitter(a), self.jitter(b));
+        self.beam(a, b);
+    }
+    fn point(&mut self, p: Vec2) {
+        let p = self.jitter(p);
+        self.beam(p, p);
+    }
+    /// Covers the beam energy below it, like an overlay on the glass.
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2) {
+        let alpha = self.color.a as f32 / 255.0;
+        let color = [self.color.r, self.color.g, self.color.b].map(|c| c as f32 / 255.0);
+        let (x0, y0) = (pos.x.max(0.0) as usize, pos.y.max(0.0) as usize);
+        let x1 = ((pos.x + size.x).max(0.0) as usize).min(self.width);
+        let y1 = ((pos.y + size.y).max(0.0) as usize).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let i = y * self.width + x;
+                for (c, color) in color.iter().enumerate() {
+                    self.beam[i][c] = self.beam[i][c] * (1.0 - alpha) + color * alpha;
+                    self.phosphor[i][c] *= 1.0 - alpha;
+                }
+            }
+        }
+    }
+    fn clear(&mut self, color: Color) {
+        self.background = color;
+        self.beam.fill([0.0; 3]);
+    }
+    /// Develops the frame: persistence, glow, scanlines and curvature.
+    fn present(&mut self) {
+        if self.settings.persistence {
+            for (phosphor, beam) in self.phosphor.iter_mut().zip(&self.beam) {
+                for c in 0..3 {
+                    phosphor[c] = (phosphor[c] * DECAY).max(beam[c]);
+                }
+            }
+        } else {
+            self.phosphor.copy_from_slice(&self.beam);
+        }
+        let mut light = self.phosphor.clone();
+        if self.settings.glow {
+            let glow = self.blur(&self.phosphor);
+            for (light, glow) in light.iter_mut().zip(glow) {
+                for c in 0..3 {
+                    light[c] += glow[c] * GLOW_STRENGTH;
+                }
+            }
+        }
+        let background = [self.background.r, self.background.g, self.background.b];
+        let mut pixels = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            let row = if self.settings.scanlines && y % 2 == 1 {
+                SCANLINE
+            } else {
+                1.0
+            };
+            for x in 0..self.width {
+                let source = if self.settings.curvature {
+                    self.curve(x, y)
+                } else {
+                    Some(y * self.width + x)
+                };
+                let Some(i) = source else {
+                    pixels.extend_from_slice(&[0, 0, 0, 255]);
+                    continue;
+                };
+                for c in 0..3 {
+                    let value = background[c] as f32 + light[i][c] * row * 255.0;
+                    pixels.push(value.min(255.0) as u8);
+                }
+                pixels.push(255);
+            }
+        }
+        self.frame = Framebuffer::from_rgba(self.width, self.height, pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain() -> MonitorSettings {
+        MonitorSettings {
+            enabled: true,
+            thickness: 3.0,
+            glow: false,
+            persistence: false,
+            jitter: false,
+            scanlines: false,
+            curvature: false,
+        }
+    }
+
+    fn horizontal_line(monitor: &mut VectorMonitor) {
+        monitor.clear(Color::BLACK);
+        monitor.set_color(Color::WHITE);
+        monitor.line(Vec2::new(5.0, 10.0), Vec2::new(25.0, 10.0));
+        monitor.present();
+    }
+
+    #[test]
+    fn beams_have_width() {
+        let mut monitor = VectorMonitor::new(32, 32, plain());
+        horizontal_line(&mut monitor);
+        let frame = monitor.frame();
+        // Three pixels wide: the centre row and one either side.
+        for y in 9..=11 {
+            assert_eq!(frame.pixel(15, y), Color::WHITE);
+        }
+        assert_eq!(frame.pixel(15, 13), Color::BLACK);
+    }
+
+    #[test]
+    fn glow_and_persistence() {
+        let mut monitor = VectorMonitor::new(32, 32, plain());
+        monitor.settings.glow = true;
+        monitor.settings.persistence = true;
+        horizontal_line(&mut monitor);
+        assert!(monitor.frame().pixel(15, 14).r > 0);
+        // The line fades over the next frames instead of vanishing.
+        monitor.clear(Color::BLACK);
+        monitor.present();
+        let faded = monitor.frame().pixel(15, 10).r;
+        assert!(faded > 0 && faded < 255);
+        for _ in 0..20 {
+            monitor.clear(Color::BLACK);
+            monitor.present();
+        }
+        assert_eq!(monitor.frame().pixel(15, 10), Color::BLACK);
+    }
+
+    #[test]
+    fn scanlines_and_curvature() {
+        let mut monitor = VectorMonitor::new(32, 32, plain());
+        monitor.settings.scanlines = true;
+        monitor.set_color(Color::WHITE);
+        monitor.fill_rect(Vec2::ZERO, Vec2::new(32.0, 32.0));
+        monitor.present();
+        assert_eq!(monitor.frame().pixel(3, 2), Color::WHITE);
+        assert!(monitor.frame().pixel(3, 3).r < 200);
+        monitor.settings.scanlines = false;
+        monitor.settings.curvature = true;
+        monitor.present();
+        // Corners fall off the tube; the middle is untouched.
+        assert_eq!(monitor.frame().pixel(0, 0), Color::BLACK);
+        assert_eq!(monitor.frame().pixel(16, 16), Color::WHITE);
+    }
+}
@@ -0,0 +1,223 @@
+//! Drawing into an RGBA buffer in memory, for tests and headless captures.
+
+use super::{Color, Renderer};
+use crate::math::Vec2;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// An RGBA image that implements `Renderer`. Lines run between the same
+/// truncated endpoints SDL's `draw_line` uses, so they land on the same
+/// pixels, but are antialiased: each step along the major axis shares its
+/// coverage between the two pixels nearest the ideal line.
+#[derive(Clone)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major RGBA bytes.
+    pixels: Vec<u8>,
+    color: Color,
+}
+impl Framebuffer {
+    /// A black image.
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        let mut framebuffer = Framebuffer {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+            color: Color::WHITE,
+        };
+        framebuffer.clear(Color::BLACK);
+        framebuffer
+    }
+    /// Wraps row-major RGBA bytes, e.g. pixels read back from a window.
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<u8>) -> Framebuffer {
+        assert_eq!(pixels.len(), width * height * 4);
+        Framebuffer {
+            width,
+            height,
+            pixels,
+            color: Color::WHITE,
+        }
+    }
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let i = (y * self.width + x) * 4;
+        let p = &self.pixels[i..i + 4];
+        Color::rgba(p[0], p[1], p[2], p[3])
+    }
+    /// Writes the image as an 8-bit RGBA PNG.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(writer.finish()?)
+    }
+    /// Reads a PNG written by `save_png`.
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Framebuffer> {
+        let mut reader = png::Decoder::new(BufReader::new(File::open(path)?)).read_info()?;
+        let info = reader.info();
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected an 8-bit RGBA PNG",
+            ));
+        }
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut pixels = vec![0; width * height * 4];
+        reader.next_frame(&mut pixels)?;
+        Ok(Framebuffer::from_rgba(width, height, pixels))
+    }
+    /// Number of pixels where some channel differs from `other` by more than
+    /// `tolerance`. Images of different sizes differ everywhere.
+    pub fn diff(&self, other: &Framebuffer, tolerance: u8) -> usize {
+        if (self.width, self.height) != (other.width, other.height) {
+            return self.width.max(other.width) * self.height.max(other.height);
+        }
+        self.pixels
+            .chunks_exact(4)
+            .zip(other.pixels.chunks_exact(4))
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(a, b)| a.abs_diff(*b) > tolerance)
+            })
+            .count()
+    }
+    /// Blends `color` over one pixel at `coverage` (0 to 1) of its alpha;
+    /// anything off the image is dropped.
+    fn plot(&mut self, x: i64, y: i64, color: Color, coverage: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = (y as usize * self.width + x as usize) * 4;
+        let alpha = (color.a as f64 * coverage).round() as u32;
+        for (channel, value) in [color.r, color.g, color.b].into_iter().enumerate() {
+            let old = self.pixels[i + channel] as u32;
+            self.pixels[i + channel] = ((value as u32 * alpha + old * (255 - alpha)) / 255) as u8;
+        }
+        self.pixels[i + 3] = 255;
+    }
+}
+impl Renderer for Framebuffer {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        // Xiaolin Wu's algorithm, walking along the major axis.
+        let (mut a, mut b) = (
+            Vec2::new(a.x.trunc(), a.y.trunc()),
+            Vec2::new(b.x.trunc(), b.y.trunc()),
+        );
+        let steep = (b.y - a.y).abs() > (b.x - a.x).abs();
+        if steep {
+            a = Vec2::new(a.y, a.x);
+            b = Vec2::new(b.y, b.x);
+        }
+        if a.x > b.x {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let gradient = if b.x == a.x {
+            0.0
+        } else {
+            (b.y - a.y) / (b.x - a.x)
+        };
+        for x in a.x as i64..=b.x as i64 {
+            let y = a.y + gradient * (x as f64 - a.x);
+            let (row, fract) = (y.floor() as i64, y - y.floor());
+            for (minor, coverage) in [(row, 1.0 - fract), (row + 1, fract)] {
+                if coverage > 0.0 {
+                    if steep {
+                        self.plot(minor, x, self.color, coverage);
+                    } else {
+                        self.plot(x, minor, self.color, coverage);
+                    }
+                }
+            }
+        }
+    }
+    fn point(&mut self, p: Vec2) {
+        self.plot(p.x as i64, p.y as i64, self.color, 1.0);
+    }
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2) {
+        for y in pos.y as i64..(pos.y + size.y) as i64 {
+            for x in pos.x as i64..(pos.x + size.x) as i64 {
+                self.plot(x, y, self.color, 1.0);
+            }
+        }
+    }
+    fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, 255]);
+        }
+    }
+    fn present(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axis_aligned_lines_are_crisp() {
+        let mut fb = Framebuffer::new(10, 10);
+        fb.set_color(Color::WHITE);
+        fb.line(Vec2::new(1.9, 2.0), Vec2::new(8.0, 2.5));
+        fb.line(Vec2::new(5.0, 4.0), Vec2::new(5.0, 8.0));
+        let lit = fb.pixels().chunks(4).filter(|p| p[0] > 0).count();
+        assert_eq!(lit, 8 + 5);
+        assert!((1..=8).all(|x| fb.pixel(x, 2) == Color::WHITE));
+        // Clipped at the edge rather than wrapping or panicking.
+        fb.line(Vec2::new(-5.0, 9.0), Vec2::new(20.0, 9.0));
+        assert_eq!(fb.pixel(9, 9), Color::WHITE);
+    }
+
+    #[test]
+    fn sloped_lines_share_coverage() {
+        let mut fb = Framebuffer::new(10, 10);
+        fb.set_color(Color::WHITE);
+        fb.line(Vec2::new(0.0, 0.0), Vec2::new(8.0, 4.0));
+        assert_eq!(fb.pixel(0, 0), Color::WHITE);
+        assert_eq!(fb.pixel(8, 4), Color::WHITE);
+        // Halfway between rows 0 and 1 at x = 1.
+        assert_eq!(fb.pixel(1, 0), Color::rgb(128, 128, 128));
+        assert_eq!(fb.pixel(1, 1), Color::rgb(128, 128, 128));
+        // Columns carry one pixel's worth of light in total.
+        for x in 0..=8 {
+            let column: u32 = (0..10).map(|y| fb.pixel(x, y).r as u32).sum();
+            assert!((254..=256).contains(&column), "{}", column);
+        }
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut fb = Framebuffer::new(6, 4);
+        fb.set_color(Color::rgb(200, 100, 50));
+        fb.line(Vec2::new(0.0, 0.0), Vec2::new(5.0, 3.0));
+        let path = std::env::temp_dir().join(format!("foxtroids-{}.png", std::process::id()));
+        fb.save_png(&path).unwrap();
+        let loaded = Framebuffer::load_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.pixels(), fb.pixels());
+        assert_eq!(loaded.diff(&fb, 0), 0);
+        assert!(Framebuffer::new(6, 4).diff(&fb, 10) >= 6);
+        assert_eq!(Framebuffer::new(6, 4).diff(&fb, 255), 0);
+        assert_eq!(Framebuffer::new(3, 3).diff(&fb, 255), 24);
+    }
+
+    #[test]
+    fn translucent_fill_blends() {
+        let mut fb = Framebuffer::new(4, 4);
+        fb.clear(Color::WHITE);
+        fb.set_color(Color::rgba(0, 0, 0, 255 / 2 + 1));
+        fb.fill_rect(Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0));
+        assert_eq!(fb.pixel(1, 1), Color::rgb(127, 127, 127));
+        assert_eq!(fb.pixel(0, 0), Color::WHITE);
+        assert_eq!(fb.pixel(3, 3), Color::WHITE);
+    }
+}
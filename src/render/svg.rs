@@ -0,0 +1,179 @@
+//! Drawing into an SVG document, for print-quality art and documentation.
+
+use super::{Color, Renderer};
+use crate::math::Vec2;
+use std::fmt::Write;
+
+/// Collects everything drawn into SVG elements. Lines that continue where
+/// the previous one of the same colour ended are joined into one polyline.
+pub struct Svg {
+    pub width: f64,
+    pub height: f64,
+    /// Width of every stroke, in the same units as the coordinates.
+    pub stroke_width: f64,
+    background: Color,
+    elements: Vec<String>,
+    // The open polyline: its colour and points so far.
+    path: Option<(Color, Vec<Vec2>)>,
+    color: Color,
+}
+impl Svg {
+    pub fn new(width: f64, height: f64, stroke_width: f64) -> Svg {
+        Svg {
+            width,
+            height,
+            stroke_width,
+            background: Color::BLACK,
+            elements: Vec::new(),
+            path: None,
+            color: Color::WHITE,
+        }
+    }
+    fn flush_path(&mut self) {
+        if let Some((color, points)) = self.path.take() {
+            let closed = points.len() > 3 && points.first() == points.last();
+            let (element, points) = if closed {
+                ("polygon", &points[..points.len() - 1])
+            } else {
+                ("polyline", &points[..])
+            };
+            self.elements.push(format!(
+                "<{} points=\"{}\" stroke=\"{}\"{}/>",
+                element,
+                coordinates(points),
+                hex(color),
+                opacity("stroke", color)
+            ));
+        }
+    }
+    /// The finished document.
+    pub fn finish(mut self) -> String {
+        self.flush_path();
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            w = round(self.width),
+            h = round(self.height)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            hex(self.background)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<g fill=\"none\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\">",
+            self.stroke_width
+        )
+        .unwrap();
+        for element in &self.elements {
+            writeln!(svg, "{}", element).unwrap();
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+impl Renderer for Svg {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        match &mut self.path {
+            Some((color, points)) if *color == self.color && points.last() == Some(&a) => {
+                points.push(b)
+            }
+            _ => {
+                self.flush_path();
+                self.path = Some((self.color, vec![a, b]));
+            }
+        }
+    }
+    fn point(&mut self, p: Vec2) {
+        self.flush_path();
+        self.elements.push(format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"none\"{}/>",
+            round(p.x),
+            round(p.y),
+            self.stroke_width / 2.0,
+            hex(self.color),
+            opacity("fill", self.color)
+        ));
+    }
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2) {
+        self.flush_path();
+        self.elements.push(format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"none\"{}/>",
+            round(pos.x),
+            round(pos.y),
+            round(size.x),
+            round(size.y),
+            hex(self.color),
+            opacity("fill", self.color)
+        ));
+    }
+    /// Starts the document over with `color` as its background.
+    fn clear(&mut self, color: Color) {
+        self.background = color;
+        self.elements.clear();
+        self.path = None;
+    }
+    fn present(&mut self) {}
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+fn opacity(attribute: &str, color: Color) -> String {
+    if color.a == 255 {
+        String::new()
+    } else {
+        format!(
+            " {}-opacity=\"{}\"",
+            attribute,
+            round(color.a as f64 / 255.0)
+        )
+    }
+}
+// Three decimals are plenty and keep the documents readable.
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+fn coordinates(points: &[Vec2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", round(p.x), round(p.y)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_lines_into_shapes() {
+        let mut svg = Svg::new(100.0, 50.0, 1.5);
+        svg.set_color(Color::rgb(255, 128, 0));
+        svg.polygon(&[
+            Vec2::new(10.0, 10.0),
+            Vec2::new(20.0, 10.0),
+            Vec2::new(15.0, 1.0 / 3.0),
+        ]);
+        svg.set_color(Color::WHITE);
+        svg.line(Vec2::new(0.0, 0.0), Vec2::new(5.0, 5.0));
+        svg.line(Vec2::new(5.0, 5.0), Vec2::new(9.0, 5.0));
+        svg.set_color(Color::rgba(0, 0, 40, 51));
+        svg.fill_rect(Vec2::ZERO, Vec2::new(4.0, 4.0));
+        let doc = svg.finish();
+        assert!(doc
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"50\""));
+        assert!(doc.contains("stroke-width=\"1.5\""));
+        assert!(doc.contains("<polygon points=\"10,10 20,10 15,0.333\" stroke=\"#ff8000\"/>"));
+        assert!(doc.contains("<polyline points=\"0,0 5,5 9,5\" stroke=\"#ffffff\"/>"));
+        assert!(doc.contains("fill=\"#000028\" stroke=\"none\" fill-opacity=\"0.2\"/>"));
+        assert!(doc.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Drawing into a grid of characters shown on an ANSI terminal.
+
+use super::{Color, Renderer};
+use crate::math::Vec2;
+use std::io::{self, Write};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Cell {
+    ch: char,
+    color: Color,
+    // Braille dots lit in this cell, one bit per dot.
+    dots: u8,
+}
+const BLANK: Cell = Cell {
+    ch: ' ',
+    color: Color::BLACK,
+    dots: 0,
+};
+// Bit of each dot in a Braille character, indexed by [row][column].
+const BRAILLE_DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Renders a `bounds` sized picture into `cols` by `rows` characters and
+/// writes them with ANSI colours to `out` on `present`. Lines are drawn either
+/// as `-`, `|`, `/` or `\` picked by slope, or in Braille mode as a 2x4 grid
+/// of dots per character. A character takes the colour last drawn into it.
+/// The first error writing to `out` is kept for `take_error`.
+pub struct Terminal<W: Write> {
+    out: W,
+    pub cols: usize,
+    pub rows: usize,
+    braille: bool,
+    // Picture pixels per character cell.
+    scale: Vec2,
+    cells: Vec<Cell>,
+    color: Color,
+    error: Option<io::Error>,
+}
+impl<W: Write> Terminal<W> {
+    pub fn new(out: W, cols: usize, rows: usize, bounds: Vec2) -> Terminal<W> {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        Terminal {
+            out,
+            cols,
+            rows,
+            braille: false,
+            scale: Vec2::new(bounds.x / cols as f64, bounds.y / rows as f64),
+            cells: vec![BLANK; cols * rows],
+            color: Color::WHITE,
+            error: None,
+        }
+    }
+    /// Like `new`, but every character holds a 2x4 grid of Braille dots.
+    pub fn braille(out: W, cols: usize, rows: usize, bounds: Vec2) -> Terminal<W> {
+        Terminal {
+            braille: true,
+            ..Terminal::new(out, cols, rows, bounds)
+        }
+    }
+    /// Fails with the first error writing since the last call, if any.
+    pub fn take_error(&mut self) -> io::Result<()> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+    /// The characters drawn since the last clear, one string per row.
+    pub fn text_rows(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.cols)
+            .map(|row| row.iter().map(|cell| cell.ch).collect())
+            .collect()
+    }
+    fn to_cell(&self, p: Vec2) -> Vec2 {
+        Vec2::new(p.x / self.scale.x, p.y / self.scale.y)
+    }
+    fn cell_mut(&mut self, p: Vec2) -> Option<&mut Cell> {
+        let (x, y) = (p.x.floor(), p.y.floor());
+        if x >= 0.0 && y >= 0.0 && (x as usize) < self.cols && (y as usize) < self.rows {
+            Some(&mut self.cells[y as usize * self.cols + x as usize])
+        } else {
+            None
+        }
+    }
+    fn set(&mut self, p: Vec2, ch: char) {
+        let color = self.color;
+        if let Some(cell) = self.cell_mut(p) {
+            *cell = Cell { ch, color, dots: 0 };
+        }
+    }
+    // Lights the dot under `p`, given in cell coordinates.
+    fn dot(&mut self, p: Vec2) {
+        let color = self.color;
+        let column = (p.x.fract() * 2.0) as usize;
+        let row = (p.y.fract() * 4.0) as usize;
+        if let Some(cell) = self.cell_mut(p) {
+            cell.dots |= BRAILLE_DOTS[row][column];
+            cell.ch = char::from_u32(0x2800 + cell.dots as u32).unwrap();
+            cell.color = color;
+        }
+    }
+}
+impl<W: Write> Renderer for Terminal<W> {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        let (a, b) = (self.to_cell(a), self.to_cell(b));
+        let d = b - a;
+        if self.braille {
+            // One step per dot along the longer axis.
+            let steps = (d.x.abs() * 2.0).max(d.y.abs() * 4.0).ceil().max(1.0) as usize;
+            for i in 0..=steps {
+                self.dot(a.lerp(b, i as f64 / steps as f64));
+            }
+            return;
+        }
+        // Cells are about twice as tall as they are wide.
+        let slope = d.y * 2.0 / d.x;
+        let ch = if d.x == 0.0 || slope.abs() > 3.0 {
+            '|'
+        } else if slope.abs() < 1.0 / 3.0 {
+            '-'
+        } else if slope > 0.0 {
+            '\\'
+        } else {
+            '/'
+        };
+        let steps = d.x.abs().max(d.y.abs()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            self.set(a.lerp(b, i as f64 / steps as f64), ch);
+        }
+    }
+    fn point(&mut self, p: Vec2) {
+        if self.braille {
+            self.dot(self.to_cell(p));
+        } else {
+            self.set(self.to_cell(p), '.');
+        }
+    }
+    /// Blanks the cells under the rectangle; opaque colours fill them with
+    /// solid blocks instead.
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2) {
+        let ch = if self.color.a == 255 { '█' } else { ' ' };
+        let (min, max) = (self.to_cell(pos), self.to_cell(pos + size));
+        for y in min.y.floor() as i64..max.y.ceil() as i64 {
+            for x in min.x.floor() as i64..max.x.ceil() as i64 {
+                self.set(Vec2::new(x as f64, y as f64), ch);
+            }
+        }
+    }
+    fn clear(&mut self, _color: Color) {
+        self.cells.fill(BLANK);
+    }
+    fn present(&mut self) {
+        // Home the cursor and repaint every row, changing colour only when needed.
+        let mut frame = String::from("\x1b[H");
+        let mut current = None;
+        for (i, row) in self.cells.chunks(self.cols).enumerate() {
+            if i > 0 {
+                frame.push_str("\r\n");
+            }
+            for cell in row {
+                if cell.ch != ' ' && current != Some(cell.color) {
+                    let Color { r, g, b, .. } = cell.color;
+                    frame.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
+                    current = Some(cell.color);
+                }
+                frame.push(cell.ch);
+            }
+        }
+        frame.push_str("\x1b[0m");
+        let written = self.out.write_all(frame.as_bytes());
+        if let Err(error) = written.and_then(|_| self.out.flush()) {
+            self.error.get_or_insert(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_pick_characters_by_slope() {
+        let mut term = Terminal::new(Vec::new(), 8, 4, Vec2::new(80.0, 80.0));
+        term.line(Vec2::new(0.0, 5.0), Vec2::new(75.0, 5.0));
+        term.line(Vec2::new(75.0, 25.0), Vec2::new(75.0, 75.0));
+        term.point(Vec2::new(5.0, 65.0));
+        assert_eq!(
+            term.text_rows(),
+            ["--------", "       |", "       |", ".      |"]
+        );
+        term.present();
+        let written = String::from_utf8(term.out.clone()).unwrap();
+        assert!(written.starts_with("\x1b[H\x1b[38;2;255;255;255m--------"));
+        term.clear(Color::BLACK);
+        assert!(term.text_rows().iter().all(|row| row.trim().is_empty()));
+    }
+
+    #[test]
+    fn braille_sets_one_dot_per_step() {
+        let mut term = Terminal::braille(Vec::new(), 2, 1, Vec2::new(40.0, 40.0));
+        // The top row of dots across both cells, then the bottom right dot.
+        term.line(Vec2::new(0.0, 0.0), Vec2::new(39.0, 0.0));
+        term.point(Vec2::new(39.0, 39.0));
+        assert_eq!(term.text_rows(), ["\u{2809}\u{2889}"]);
+        term.set_color(Color::rgba(0, 0, 0, 100));
+        term.fill_rect(Vec2::ZERO, Vec2::new(20.0, 40.0));
+        assert_eq!(term.text_rows(), [" \u{2889}"]);
+    }
+    #[test]
+    fn empty_grids_and_failed_writes_do_not_panic() {
+        // A full buffer fails to write like a closed pipe would.
+        let mut full = [0u8; 4];
+        let mut term = Terminal::new(&mut full[..], 0, 0, Vec2::new(40.0, 40.0));
+        term.point(Vec2::new(5.0, 5.0));
+        assert_eq!(term.text_rows(), ["."]);
+        term.present();
+        term.present();
+        assert!(term.take_error().is_err());
+        assert!(term.take_error().is_ok());
+    }
+}
@@ -0,0 +1,378 @@
+//! Recorded games: the seed and rules plus the ships' input for every tick,
+//! which is all it takes to simulate a game again frame for frame.
+
+use crate::ai::Pilot;
+use crate::control::{Observation, ShipController};
+use crate::input::ShipInput;
+use crate::settings::Rules;
+use crate::world::World;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "foxtroids replay 1";
+// Button letters in the text format, in ShipInput field order.
+const BUTTONS: [char; 6] = ['L', 'R', 'T', 'B', 'F', 'H'];
+// Longest recording a replay file may hold: ten hours at 60 ticks a second.
+const MAX_FRAMES: usize = 10 * 60 * 60 * 60;
+
+/// A seed, the rules and one `ShipInput` per ship and tick. Stored as text: a
+/// header, a `seed` line, a `name value` line for every rule not at its
+/// default, then runs of `count buttons...` with buttons for each ship in
+/// turn, as letters from `LRTBFH` (left, right, thrust, back, fire,
+/// hyperspace) or `-` for none.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: Rules,
+    /// Per tick, one input for each of `rules.players` ships.
+    pub inputs: Vec<Vec<ShipInput>>,
+}
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        Replay {
+            seed,
+            rules: Rules::default(),
+            inputs: Vec::new(),
+        }
+    }
+    /// The world this replay starts from.
+    pub fn world(&self) -> World {
+        World::with_rules(self.rules.clone(), self.seed)
+    }
+    pub fn record(&mut self, inputs: Vec<ShipInput>) {
+        self.inputs.push(inputs);
+    }
+    /// Inputs for tick `frame`, or `None` once the recording has run out.
+    pub fn input(&self, frame: u64) -> Option<&[ShipInput]> {
+        self.inputs.get(frame as usize).map(Vec::as_slice)
+    }
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        if lines.next() != Some(HEADER) {
+            return Err(format!("missing \"{}\" header", HEADER));
+        }
+        let seed = lines
+            .next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .and_then(|seed| seed.trim().parse().ok())
+            .ok_or("missing seed line")?;
+        let mut replay = Replay::new(seed);
+        let mut lines = lines.peekable();
+        while let Some(line) = lines.next_if(|line| !line.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let (key, value) = line
+                .split_once(' ')
+                .ok_or_else(|| format!("bad line: {}", line))?;
+            if !replay.rules.set(key, value.trim())? {
+                return Err(format!("unknown rule: {}", key));
+            }
+        }
+        for line in lines {
+            let mut words = line.split_whitespace();
+            let count: usize = words
+                .next()
+                .and_then(|count| count.parse().ok())
+                .ok_or_else(|| format!("bad count: {}", line))?;
+            let inputs = words
+                .map(decode)
+                .collect::<Option<Vec<_>>>()
+                .filter(|inputs| inputs.len() == replay.rules.players)
+                .ok_or_else(|| format!("bad buttons: {}", line))?;
+            if replay.inputs.len().saturating_add(count) > MAX_FRAMES {
+                return Err(format!("longer than {} frames", MAX_FRAMES));
+            }
+            replay.inputs.extend(std::iter::repeat_n(inputs, count));
+        }
+        Ok(replay)
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Replay> {
+        Replay::parse(&std::fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "seed {}", self.seed)?;
+        let defaults = Rules::default().entries();
+        for (rule, default) in self.rules.entries().into_iter().zip(defaults) {
+            if rule != default {
+                writeln!(f, "{} {}", rule.0, rule.1)?;
+            }
+        }
+        let mut rest = &self.inputs[..];
+        while let Some(first) = rest.first() {
+            let run = rest.iter().take_while(|inputs| *inputs == first).count();
+            let buttons: Vec<String> = first.iter().map(encode).collect();
+            writeln!(f, "{} {}", run, buttons.join(" "))?;
+            rest = &rest[run..];
+        }
+        Ok(())
+    }
+}
+
+/// Flies a ship the way one player of a recording did, then leaves it
+/// alone once the recording runs out.
+pub struct ReplayController {
+    inputs: Vec<ShipInput>,
+}
+impl ReplayController {
+    pub fn new(replay: &Replay, player: usize) -> ReplayController {
+        ReplayController {
+            inputs: replay
+                .inputs
+                .iter()
+                .map(|inputs| inputs.get(player).copied().unwrap_or_default())
+                .collect(),
+        }
+    }
+}
+impl ShipController for ReplayController {
+    fn command(&mut self, observation: &Observation) -> ShipInput {
+        let frame = observation.world.frame as usize;
+        self.inputs.get(frame).copied().unwrap_or_default()
+    }
+}
+
+/// Where a frontend gets each tick's input: from a replay while it lasts,
+/// then from the players. Whatever is used can be recorded to a file.
+pub struct Session {
+    playback: Option<Replay>,
+    recording: Replay,
+    record_to: Option<PathBuf>,
+    // Computer pilots for the last ships, in order.
+    pilots: Vec<Box<dyn ShipController>>,
+}
+impl Session {
+    /// Plays `playback` if given, otherwise a fresh game from `seed` played
+    /// by `rules`.
+    pub fn new(
+        seed: u64,
+        rules: Rules,
+        playback: Option<Replay>,
+        record_to: Option<PathBuf>,
+    ) -> Session {
+        let (seed, rules) = match &playback {
+            Some(replay) => (replay.seed, replay.rules.clone()),
+            None => (seed, rules),
+        };
+        Session {
+            playback,
+            recording: Replay {
+                rules,
+                ..Replay::new(seed)
+            },
+            record_to,
+            pilots: Vec::new(),
+        }
+    }
+    /// Hands the last `cpu` ships to the built-in pilot.
+    pub fn set_cpu(&mut self, cpu: usize) {
+        self.set_pilots((0..cpu).map(|_| Box::new(Pilot) as _).collect());
+    }
+    /// Hands the last ships to `pilots`, one each, such as trained ones.
+    pub fn set_pilots(&mut self, mut pilots: Vec<Box<dyn ShipController>>) {
+        pilots.truncate(self.players());
+        self.pilots = pilots;
+    }
+    pub fn world(&self) -> World {
+        self.recording.world()
+    }
+    /// Length of the replay being played, if any.
+    pub fn replay_len(&self) -> Option<u64> {
+        self.playback
+            .as_ref()
+            .map(|replay| replay.inputs.len() as u64)
+    }
+    /// Players in the game being played.
+    pub fn players(&self) -> usize {
+        self.recording.rules.players
+    }
+    /// Inputs for the tick of `world` about to be simulated, one per ship;
+    /// `live` is what the players are pressing, in player order.
+    pub fn input(&mut self, world: &World, live: &[ShipInput]) -> Vec<ShipInput> {
+        let inputs = match self
+            .playback
+            .as_ref()
+            .and_then(|replay| replay.input(world.frame))
+        {
+            Some(inputs) => inputs.to_vec(),
+            None => {
+                let players = self.players();
+                let mut inputs: Vec<ShipInput> = (0..players)
+                    .map(|i| live.get(i).copied().unwrap_or_default())
+                    .collect();
+                let first = players - self.pilots.len();
+                for (i, pilot) in self.pilots.iter_mut().enumerate() {
+                    let ship = first + i;
+                    inputs[ship] = pilot.command(&Observation { world, ship });
+                }
+                inputs
+            }
+        };
+        self.recording.record(inputs.clone());
+        inputs
+    }
+    /// Records `replay` in place of what went through `input`, such as the
+    /// confirmed inputs of an online game.
+    pub fn set_recording(&mut self, replay: Replay) {
+        self.recording = replay;
+    }
+    /// Writes the recording, if one was asked for.
+    pub fn save(&self) -> io::Result<()> {
+        match &self.record_to {
+            Some(path) => self.recording.save(path),
+            None => Ok(()),
+        }
+    }
+}
+
+fn encode(input: &ShipInput) -> String {
+    let held: String = BUTTONS
+        .iter()
+        .zip(input.buttons())
+        .filter(|(_, held)| *held)
+        .map(|(letter, _)| letter)
+        .collect();
+    if held.is_empty() {
+        "-".into()
+    } else {
+        held
+    }
+}
+fn decode(buttons: &str) -> Option<ShipInput> {
+    let mut input = ShipInput::default();
+    for c in buttons.chars().filter(|c| *c != '-') {
+        let held = match c {
+            'L' => &mut input.left,
+            'R' => &mut input.right,
+            'T' => &mut input.thrust,
+            'B' => &mut input.reverse,
+            'F' => &mut input.fire,
+            'H' => &mut input.hyperspace,
+            _ => return None,
+        };
+        *held = true;
+    }
+    Some(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::PLAYFIELD;
+
+    #[test]
+    fn text_round_trip() {
+        let mut replay = Replay::new(99);
+        let fire = ShipInput {
+            fire: true,
+            ..ShipInput::default()
+        };
+        let turn = ShipInput {
+            left: true,
+            thrust: true,
+            ..ShipInput::default()
+        };
+        for input in [fire, fire, turn, ShipInput::default()] {
+            replay.record(vec![input]);
+        }
+        let text = replay.to_string();
+        assert_eq!(text, "foxtroids replay 1\nseed 99\n2 F\n1 LT\n1 -\n");
+        assert_eq!(Replay::parse(&text).unwrap(), replay);
+        assert_eq!(replay.input(2), Some(&[turn][..]));
+        assert_eq!(replay.input(4), None);
+    }
+
+    #[test]
+    fn rules_and_players_round_trip() {
+        let mut replay = Replay::new(99);
+        replay.rules.arena = 3;
+        replay.rules.players = 2;
+        let fire = ShipInput {
+            fire: true,
+            ..ShipInput::default()
+        };
+        replay.record(vec![fire, ShipInput::default()]);
+        let text = replay.to_string();
+        assert_eq!(
+            text,
+            "foxtroids replay 1\nseed 99\narena 3\nplayers 2\n1 F -\n"
+        );
+        assert_eq!(Replay::parse(&text).unwrap(), replay);
+        assert_eq!(replay.world().bounds, PLAYFIELD * 3.0);
+        assert_eq!(replay.world().ships.len(), 2);
+        // Every line needs buttons for each ship.
+        assert!(Replay::parse("foxtroids replay 1\nseed 1\nplayers 2\n1 F\n").is_err());
+    }
+
+    #[test]
+    fn controllers_fly_like_the_recording() {
+        let mut replay = Replay::new(7);
+        replay.rules.players = 2;
+        let jump = ShipInput {
+            hyperspace: true,
+            thrust: true,
+            ..ShipInput::default()
+        };
+        for frame in 0..200 {
+            replay.record(vec![ShipInput::from_bits(frame as u8 % 32), jump]);
+        }
+        assert!(replay.to_string().contains(" TH\n"));
+        let mut controllers: Vec<Box<dyn ShipController>> = (0..2)
+            .map(|player| Box::new(ReplayController::new(&replay, player)) as _)
+            .collect();
+        let mut flown = replay.world();
+        let mut played = replay.world();
+        for frame in 0..200 {
+            flown.step(&crate::control::commands(&flown, &mut controllers));
+            played.step(replay.input(frame).unwrap());
+        }
+        assert_eq!(flown.checksum(), played.checksum());
+        assert!(flown.ships[1].hyperspace > 0);
+    }
+
+    #[test]
+    fn session_plays_back_then_goes_live() {
+        let mut replay = Replay::new(5);
+        let fire = ShipInput {
+            fire: true,
+            ..ShipInput::default()
+        };
+        replay.record(vec![fire]);
+        let mut session = Session::new(1, Rules::default(), Some(replay), None);
+        let live = ShipInput {
+            right: true,
+            ..ShipInput::default()
+        };
+        assert_eq!(session.world().seed, 5);
+        let mut world = session.world();
+        assert_eq!(session.input(&world, &[live]), [fire]);
+        world.step(&[fire]);
+        assert_eq!(session.input(&world, &[live]), [live]);
+        assert_eq!(session.recording.inputs, [[fire], [live]]);
+        assert_eq!(session.replay_len(), Some(1));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(Replay::parse("seed 1\n").is_err());
+        assert!(Replay::parse("foxtroids replay 1\nseed x\n").is_err());
+        assert!(Replay::parse("foxtroids replay 1\nseed 1\n3 Q\n").is_err());
+        assert!(Replay::parse("foxtroids replay 1\nseed 1\n99999999999999 F\n").is_err());
+        let long = format!(
+            "foxtroids replay 1\nseed 1\n{0} F\n{0} -\n",
+            MAX_FRAMES / 2 + 1
+        );
+        assert!(Replay::parse(&long).is_err());
+        let replay = Replay::parse("foxtroids replay 1\n# comment\nseed 1\n3 RF\n").unwrap();
+        assert_eq!(replay.inputs.len(), 3);
+    }
+}
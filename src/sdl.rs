@@ -0,0 +1,492 @@
+//! The windowed frontend: an SDL window, keyboard input and sound.
+
+use crate::ai;
+use crate::attract::Demo;
+#[cfg(feature = "audio")]
+use crate::audio::SquareWave;
+use crate::capture::{screenshot_name, FrameRecorder};
+#[cfg(feature = "dev")]
+use crate::console::{Console, ConsoleKey};
+use crate::control::{Merged, Observation, ShipController};
+use crate::debug::DebugOverlay;
+use crate::input::ShipInput;
+use crate::math::Vec2;
+use crate::net::Link;
+use crate::render::monitor::VectorMonitor;
+use crate::render::software::Framebuffer;
+use crate::render::{Color, Renderer, Transform, Viewport};
+use crate::replay::Session;
+use crate::settings::Settings;
+use crate::world::{World, PLAYFIELD};
+#[cfg(feature = "audio")]
+use sdl2::audio::{AudioDevice, AudioSpecDesired};
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::{FullscreenType, Window};
+use sdl2::EventPump;
+use std::time::{Duration, Instant};
+
+impl From<Color> for sdl2::pixels::Color {
+    fn from(color: Color) -> sdl2::pixels::Color {
+        sdl2::pixels::Color::RGBA(color.r, color.g, color.b, color.a)
+    }
+}
+fn sdl_point(v: Vec2) -> sdl2::rect::Point {
+    sdl2::rect::Point::new(v.x as i32, v.y as i32)
+}
+impl Renderer for Canvas<Window> {
+    fn set_color(&mut self, color: Color) {
+        self.set_draw_color(color);
+    }
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        self.draw_line(sdl_point(a), sdl_point(b)).unwrap();
+    }
+    fn point(&mut self, p: Vec2) {
+        self.draw_point(sdl_point(p)).unwrap();
+    }
+    fn fill_rect(&mut self, pos: Vec2, size: Vec2) {
+        let translucent = self.draw_color().a < 255;
+        if translucent {
+            self.set_blend_mode(BlendMode::Blend);
+        }
+        Canvas::fill_rect(
+            self,
+            sdl2::rect::Rect::new(pos.x as i32, pos.y as i32, size.x as u32, size.y as u32),
+        )
+        .unwrap();
+        if translucent {
+            self.set_blend_mode(BlendMode::None);
+        }
+    }
+    fn clear(&mut self, color: Color) {
+        self.set_draw_color(color);
+        Canvas::clear(self);
+    }
+    fn present(&mut self) {
+        Canvas::present(self);
+    }
+}
+#[cfg(feature = "audio")]
+fn open_audio(sdl_context: &sdl2::Sdl) -> AudioDevice<SquareWave> {
+    let desired_spec = AudioSpecDesired {
+        freq: Some(60000),
+        channels: Some(1), // mono
+        samples: None,     // default sample size
+    };
+    sdl_context
+        .audio()
+        .unwrap()
+        .open_playback(None, &desired_spec, |spec| {
+            SquareWave::new(440.0, spec.freq, 0.25)
+        })
+        .unwrap()
+}
+// Keyboard controls of the first two players: left, right, thrust,
+// reverse, fire and hyperspace.
+const KEYBOARD: [[Scancode; 6]; 2] = [
+    [
+        Scancode::A,
+        Scancode::D,
+        Scancode::W,
+        Scancode::S,
+        Scancode::Space,
+        Scancode::LShift,
+    ],
+    [
+        Scancode::Left,
+        Scancode::Right,
+        Scancode::Up,
+        Scancode::Down,
+        Scancode::RCtrl,
+        Scancode::RShift,
+    ],
+];
+// How far a stick must be pushed to count, out of 32767.
+const DEAD_ZONE: i16 = 12000;
+
+// One player's keys. SDL only reads the keyboard through the event pump, so
+// the keys are read once a frame and the controller hands that on.
+struct Keyboard {
+    keys: [Scancode; 6],
+    held: ShipInput,
+}
+impl Keyboard {
+    fn read(&mut self, event_pump: &EventPump) {
+        let state = event_pump.keyboard_state();
+        self.held = ShipInput::from_buttons(self.keys.map(|key| state.is_scancode_pressed(key)));
+    }
+}
+impl ShipController for Keyboard {
+    fn command(&mut self, _: &Observation) -> ShipInput {
+        self.held
+    }
+}
+// The D-pad or left stick turns and thrusts; A or the right shoulder fires,
+// B thrusts too and Y jumps to hyperspace.
+struct Gamepad(GameController);
+impl ShipController for Gamepad {
+    fn command(&mut self, _: &Observation) -> ShipInput {
+        let pad = &self.0;
+        let x = pad.axis(Axis::LeftX);
+        let y = pad.axis(Axis::LeftY);
+        ShipInput {
+            left: pad.button(Button::DPadLeft) || x < -DEAD_ZONE,
+            right: pad.button(Button::DPadRight) || x > DEAD_ZONE,
+            thrust: pad.button(Button::DPadUp) || pad.button(Button::B) || y < -DEAD_ZONE,
+            reverse: pad.button(Button::DPadDown) || y > DEAD_ZONE,
+            fire: pad.button(Button::A) || pad.button(Button::RightShoulder),
+            hyperspace: pad.button(Button::Y),
+        }
+    }
+}
+// Input for each of `players` ships of `world`. Player one has WASD, Space
+// and left Shift, player two the arrows, right Ctrl and right Shift; alone,
+// player one has both. The Nth gamepad also drives the Nth ship.
+fn read_inputs(
+    event_pump: &EventPump,
+    keyboards: &mut [Keyboard],
+    pads: &mut [Gamepad],
+    world: &World,
+    players: usize,
+) -> Vec<ShipInput> {
+    for keyboard in keyboards.iter_mut() {
+        keyboard.read(event_pump);
+    }
+    (0..players)
+        .map(|ship| {
+            let keys = keyboards
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| *i == ship || players == 1)
+                .map(|(_, keyboard)| keyboard as &mut dyn ShipController);
+            let pad = pads.get_mut(ship).map(|pad| pad as &mut dyn ShipController);
+            Merged(keys.chain(pad).collect()).command(&Observation { world, ship })
+        })
+        .collect()
+}
+#[cfg(feature = "dev")]
+fn console_key(key: Keycode) -> Option<ConsoleKey> {
+    match key {
+        Keycode::Backquote => Some(ConsoleKey::Toggle),
+        Keycode::Return | Keycode::KpEnter => Some(ConsoleKey::Enter),
+        Keycode::Backspace => Some(ConsoleKey::Backspace),
+        Keycode::Tab => Some(ConsoleKey::Tab),
+        Keycode::Up => Some(ConsoleKey::Up),
+        Keycode::Down => Some(ConsoleKey::Down),
+        Keycode::Escape => Some(ConsoleKey::Escape),
+        _ => None,
+    }
+}
+// The SDL format whose bytes in memory are R, G, B, A like `Framebuffer`'s.
+// ABGR8888 packs a u32 with R in the low byte, so it is RGBA on little-endian
+// machines.
+fn pixel_format() -> PixelFormatEnum {
+    if cfg!(target_endian = "little") {
+        PixelFormatEnum::ABGR8888
+    } else {
+        PixelFormatEnum::RGBA8888
+    }
+}
+fn sdl_rect(viewport: &Viewport) -> Rect {
+    Rect::new(
+        viewport.offset.x as i32,
+        viewport.offset.y as i32,
+        viewport.size.x.round() as u32,
+        viewport.size.y.round() as u32,
+    )
+}
+// What is currently in the playfield part of the window's back buffer.
+fn read_frame(canvas: &Canvas<Window>, viewport: &Viewport) -> Framebuffer {
+    let rect = sdl_rect(viewport);
+    let pixels = canvas.read_pixels(rect, pixel_format()).unwrap();
+    Framebuffer::from_rgba(rect.width() as usize, rect.height() as usize, pixels)
+}
+fn toggle_fullscreen(canvas: &mut Canvas<Window>) {
+    let window = canvas.window_mut();
+    let next = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    if let Err(error) = window.set_fullscreen(next) {
+        eprintln!("could not change fullscreen mode: {}", error);
+    }
+}
+/// Opens the game window and plays until it is closed. The playfield keeps
+/// its logical size and is scaled into the window as `settings` say, with
+/// black bars around it; Alt+Enter toggles fullscreen. F12 saves a screenshot
+/// to the working directory; `frames` records every frame. F4 toggles the
+/// vector monitor look configured in `settings`. With a `link` of peers the
+/// game is played online and the local keys and first gamepad fly its ship;
+/// watching, the window shows someone else's game. A `demo` plays behind
+/// the title screen until Space, Enter or a gamepad's A or Start starts the
+/// game.
+pub fn run(
+    mut session: Session,
+    mut link: Option<Link>,
+    mut demo: Option<Demo>,
+    mut frames: Option<FrameRecorder>,
+    settings: &Settings,
+) {
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    #[cfg(feature = "audio")]
+    let _shoot_sound = open_audio(&sdl_context);
+    let window = video_subsystem
+        .window("L'asteroids", PLAYFIELD.x as u32, PLAYFIELD.y as u32)
+        .allow_highdpi()
+        .resizable()
+        .build()
+        .unwrap();
+
+    let mut canvas = window.into_canvas().build().unwrap();
+    if settings.display.fullscreen {
+        toggle_fullscreen(&mut canvas);
+    }
+    Renderer::clear(&mut canvas, Color::BLACK);
+    canvas.present();
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    // Gamepads are opened as SDL reports them, including those already
+    // plugged in at startup.
+    let controllers = sdl_context.game_controller().unwrap();
+    let mut pads: Vec<Gamepad> = Vec::new();
+    let mut keyboards = KEYBOARD.map(|keys| Keyboard {
+        keys,
+        held: ShipInput::default(),
+    });
+
+    let mut world = session.world();
+    let mut overlay = DebugOverlay::new();
+    let texture_creator = canvas.texture_creator();
+    // The monitor draws the game at the size it appears on screen, then goes
+    // up as a texture; both are remade when that size changes.
+    let mut monitor = VectorMonitor::new(1, 1, settings.monitor.clone());
+    let mut screen = texture_creator
+        .create_texture_streaming(pixel_format(), 1, 1)
+        .unwrap();
+    #[cfg(feature = "dev")]
+    let mut console = Console::new();
+    #[cfg(feature = "dev")]
+    let mut step_budget = 0.0;
+    let mut last_start = Instant::now();
+    let mut desynced = false;
+
+    'running: loop {
+        let start = Instant::now();
+        let mut screenshot = false;
+        for event in event_pump.poll_iter() {
+            #[cfg(feature = "dev")]
+            let used = match &event {
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => console.key(console_key(*key), &mut world),
+                Event::TextInput { text, .. } => console.text(text),
+                Event::KeyUp { .. } => console.open,
+                _ => false,
+            };
+            #[cfg(feature = "dev")]
+            if used {
+                continue;
+            }
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    toggle_fullscreen(&mut canvas)
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space | Keycode::Return),
+                    ..
+                }
+                | Event::ControllerButtonDown {
+                    button: Button::A | Button::Start,
+                    ..
+                } if demo.is_some() => demo = None,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    ..
+                } => overlay.toggle(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    ..
+                } => monitor.settings.enabled = !monitor.settings.enabled,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    ..
+                } => screenshot = true,
+                Event::ControllerDeviceAdded { which, .. } => match controllers.open(which) {
+                    Ok(pad) => pads.push(Gamepad(pad)),
+                    Err(error) => eprintln!("could not open gamepad {}: {}", which, error),
+                },
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    pads.retain(|pad| pad.0.instance_id() != which)
+                }
+                #[cfg(feature = "dev")]
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => {
+                    if let Some(c) = char::from_u32(key as i32 as u32) {
+                        crate::console::debug_key(c, &mut world);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // The dev console can pause the game or run it faster or slower.
+        #[cfg(feature = "dev")]
+        let steps = if console.open {
+            0
+        } else {
+            step_budget += console.timescale;
+            let steps = step_budget as u32;
+            step_budget -= steps as f64;
+            steps
+        };
+        #[cfg(not(feature = "dev"))]
+        let steps = 1;
+        // The console's autopilot cheat hands the first ship to the AI.
+        #[cfg(feature = "dev")]
+        let autopilot = console.autopilot;
+        #[cfg(not(feature = "dev"))]
+        let autopilot = false;
+        match &mut link {
+            Some(Link::Peers(net)) => {
+                let input = read_inputs(&event_pump, &mut keyboards, &mut pads, net.world(), 1)[0];
+                if let Err(error) = net.tick(input) {
+                    eprintln!("network error: {}", error);
+                    break 'running;
+                }
+                if let Some(desync) = net.desync().filter(|_| !desynced) {
+                    eprintln!(
+                        "out of sync with player {} since frame {}",
+                        desync.player, desync.frame
+                    );
+                    desynced = true;
+                }
+            }
+            Some(Link::Watch(spectator)) => {
+                if let Err(error) = spectator.tick() {
+                    eprintln!("network error: {}", error);
+                    break 'running;
+                }
+            }
+            _ if demo.is_some() => {
+                if let Some(demo) = &mut demo {
+                    for _ in 0..steps {
+                        demo.step();
+                    }
+                }
+            }
+            link => {
+                let mut live = read_inputs(
+                    &event_pump,
+                    &mut keyboards,
+                    &mut pads,
+                    &world,
+                    session.players(),
+                );
+                for _ in 0..steps {
+                    if autopilot {
+                        ai::take_over(&world, &mut live, [0]);
+                    }
+                    let inputs = session.input(&world, &live);
+                    if let Some(Link::Broadcast(broadcaster)) = link {
+                        if let Err(error) = broadcaster.frame(&world, &inputs) {
+                            eprintln!("could not broadcast: {}", error);
+                        }
+                    }
+                    world.step(&inputs);
+                }
+            }
+        }
+        // Online or watching, the link owns the world that is shown; until
+        // the first keyframe arrives a spectator sees an empty game.
+        let world = match (&link, &demo) {
+            (_, Some(demo)) => &demo.world,
+            (Some(Link::Peers(net)), _) => net.world(),
+            (Some(Link::Watch(spectator)), _) => spectator.world().unwrap_or(&world),
+            _ => &world,
+        };
+        let draw = |r: &mut dyn Renderer| match &demo {
+            Some(demo) => demo.draw(r),
+            None => world.draw(r),
+        };
+
+        let (width, height) = canvas.output_size().unwrap();
+        let viewport = Viewport::fit(
+            world.camera.view,
+            Vec2::new(width as f64, height as f64),
+            settings.display.scaling,
+        );
+        let rect = sdl_rect(&viewport);
+        Renderer::clear(&mut canvas, Color::BLACK);
+        // Wrapped shapes stick out of the playfield; keep them off the bars.
+        canvas.set_clip_rect(rect);
+        if monitor.settings.enabled {
+            let size = (rect.width() as usize, rect.height() as usize);
+            if (monitor.frame().width, monitor.frame().height) != size {
+                monitor = VectorMonitor::new(size.0, size.1, monitor.settings.clone());
+                screen = texture_creator
+                    .create_texture_streaming(pixel_format(), rect.width(), rect.height())
+                    .unwrap();
+            }
+            monitor.clear(Color::BLACK);
+            draw(&mut Transform {
+                inner: &mut monitor,
+                scale: viewport.scale,
+                offset: Vec2::ZERO,
+            });
+            monitor.present();
+            screen
+                .update(None, monitor.frame().pixels(), size.0 * 4)
+                .unwrap();
+            canvas.copy(&screen, None, rect).unwrap();
+        } else {
+            draw(&mut viewport.transform(&mut canvas));
+        }
+        overlay.draw(world, &mut viewport.transform(&mut canvas));
+        #[cfg(feature = "dev")]
+        console.draw(world.camera.view, &mut viewport.transform(&mut canvas));
+        canvas.set_clip_rect(None);
+        // Read back before presenting, while the back buffer still holds the frame.
+        if screenshot {
+            let name = screenshot_name();
+            match read_frame(&canvas, &viewport).save_png(&name) {
+                Ok(()) => println!("saved {}", name),
+                Err(error) => eprintln!("could not save {}: {}", name, error),
+            }
+        }
+        if let Some(recorder) = &mut frames {
+            if let Err(error) = recorder.offer(|| read_frame(&canvas, &viewport)) {
+                eprintln!("could not record frame: {}", error);
+                frames = None;
+            }
+        }
+        canvas.present();
+
+        let delta_time = start.elapsed();
+        overlay.record(delta_time, start - last_start);
+        last_start = start;
+        let frame_dur = Duration::new(0, 1_000_000_000u32 / 60);
+        if delta_time <= frame_dur {
+            let sleep = frame_dur - delta_time;
+            ::std::thread::sleep(sleep);
+        }
+    }
+    if let Some(Link::Peers(net)) = &link {
+        session.set_recording(net.replay());
+    }
+    if let Err(error) = session.save() {
+        eprintln!("could not save the replay: {}", error);
+    }
+}
@@ -0,0 +1,175 @@
+//! Ray-cast sensors: rays spread evenly around the ship from its nose, each
+//! reporting how far it got and what it ran into. The rays turn with the
+//! ship, go across the edges like everything bound to the playfield, and
+//! stop at the outlines that collisions use. There are as many numbers
+//! however busy the playfield gets.
+
+use crate::control::Observation;
+use crate::math::Vec2;
+use crate::shape::Shape;
+use std::f64::consts::TAU;
+
+/// What a ray ran into. The game has no saucers yet, so rays cannot meet one.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Hit {
+    /// An asteroid by `size_tier`.
+    Asteroid(u8),
+    Ship,
+    Bullet,
+}
+
+/// One ray, `angle` from the ship's heading, clockwise: how far it got, up
+/// to the range it was cast with, and what stopped it.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Ray {
+    pub angle: f64,
+    pub distance: f64,
+    pub hit: Option<Hit>,
+}
+
+/// Asteroids as small (0), medium (1) and large (2).
+pub fn size_tier(scale: f64) -> u8 {
+    match scale {
+        scale if scale >= 40.0 => 2,
+        scale if scale >= 20.0 => 1,
+        _ => 0,
+    }
+}
+
+/// `count` rays from the nose of the observed ship, the first straight
+/// ahead, each `range` pixels long. They pass through the ship's own bullets,
+/// which cannot hurt it.
+pub fn cast(observation: &Observation, count: usize, range: f64) -> Vec<Ray> {
+    let world = observation.world;
+    let me = &observation.own().shape;
+    let nose = me.pos + Vec2::from_angle(me.rot) * me.scale;
+    let others = world
+        .ships
+        .iter()
+        .enumerate()
+        .filter(|(i, ship)| *i != observation.ship && ship.alive)
+        .map(|(_, ship)| (&ship.shape, Hit::Ship));
+    let bullets = world
+        .bullets
+        .values()
+        .filter(|bullet| bullet.owner != observation.ship)
+        .map(|bullet| (bullet, Hit::Bullet));
+    let asteroids = world
+        .asteroids
+        .values()
+        .map(|asteroid| (asteroid, Hit::Asteroid(size_tier(asteroid.scale))));
+    // Every copy of everything the rays could reach, as outlines.
+    let mut targets = Vec::new();
+    for (shape, hit) in asteroids.chain(others).chain(bullets) {
+        for shift in shifts(shape, nose, world.bounds) {
+            if (shape.pos + shift - nose).length() <= range + shape.scale {
+                targets.push((shape.verticies(shift), hit));
+            }
+        }
+    }
+    (0..count)
+        .map(|i| {
+            let angle = i as f64 * TAU / count as f64;
+            let direction = Vec2::from_angle(me.rot + angle);
+            let mut ray = Ray {
+                angle,
+                distance: range,
+                hit: None,
+            };
+            for (outline, hit) in &targets {
+                if let Some(distance) = crossing(nose, direction, outline) {
+                    if distance < ray.distance {
+                        ray.distance = distance;
+                        ray.hit = Some(*hit);
+                    }
+                }
+            }
+            ray
+        })
+        .collect()
+}
+
+// Offsets of the copies of `shape` around `from`: the nearest one and its
+// neighbours across each edge for a bound shape, the shape itself otherwise.
+fn shifts(shape: &Shape, from: Vec2, bounds: Vec2) -> Vec<Vec2> {
+    if !shape.bound {
+        return vec![Vec2::ZERO];
+    }
+    let nearest = from + from.wrapped_delta(shape.pos, bounds) - shape.pos;
+    let mut shifts = Vec::with_capacity(9);
+    for y in -1..=1 {
+        for x in -1..=1 {
+            shifts.push(nearest + Vec2::new(x as f64 * bounds.x, y as f64 * bounds.y));
+        }
+    }
+    shifts
+}
+
+// How far along the ray from `origin` in the unit `direction` it first
+// crosses the closed `outline`.
+fn crossing(origin: Vec2, direction: Vec2, outline: &[Vec2]) -> Option<f64> {
+    let mut nearest: Option<f64> = None;
+    for (i, a) in outline.iter().enumerate() {
+        let edge = outline[(i + 1) % outline.len()] - *a;
+        let denominator = direction.cross(edge);
+        if denominator.abs() < 1e-12 {
+            continue;
+        }
+        let to_a = *a - origin;
+        let along_ray = to_a.cross(edge) / denominator;
+        let along_edge = to_a.cross(direction) / denominator;
+        if along_ray >= 0.0 && (0.0..=1.0).contains(&along_edge) {
+            nearest = Some(nearest.map_or(along_ray, |nearest| nearest.min(along_ray)));
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Color;
+    use crate::shape::ShapeType;
+    use crate::world::{World, PLAYFIELD};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn rays_hit_outlines_across_the_edges() {
+        let mut world = World::new(PLAYFIELD, 1);
+        let mut rng = StdRng::seed_from_u64(3);
+        // The ship points up from near the top, at an asteroid that has
+        // wrapped to the bottom.
+        let ship = &mut world.ships[0].shape;
+        ship.pos = Vec2::new(300.0, 60.0);
+        let nose = ship.pos.y - ship.scale;
+        let mut asteroid = Shape::new(
+            Vec2::new(300.0, 560.0),
+            0.0,
+            50.0,
+            Color::WHITE,
+            ShapeType::Asteroid(0.0),
+            &mut rng,
+        );
+        asteroid.set_bound();
+        let outline = asteroid.verticies(Vec2::new(0.0, -PLAYFIELD.y));
+        world.asteroids.insert(asteroid);
+        let rays = cast(
+            &Observation {
+                world: &world,
+                ship: 0,
+            },
+            8,
+            200.0,
+        );
+        assert_eq!(rays.len(), 8);
+        assert_eq!(rays[0].hit, Some(Hit::Asteroid(2)));
+        // Where the outline crosses straight up from the nose.
+        let bottom = outline.iter().map(|point| point.y).fold(f64::MIN, f64::max);
+        assert!(rays[0].distance <= nose - (560.0 - PLAYFIELD.y) && rays[0].distance > 0.0);
+        assert!(rays[0].distance >= nose - bottom - 1e-9);
+        // Straight back there is nothing within range.
+        assert_eq!(rays[4].hit, None);
+        assert_eq!(rays[4].distance, 200.0);
+    }
+}
@@ -0,0 +1,281 @@
+//! Player settings, read from a `key = value` text file.
+
+use crate::math::Vec2;
+use crate::world::PLAYFIELD;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Optional "vector monitor" post-processing; see `render::monitor`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MonitorSettings {
+    /// Draw through the monitor at all.
+    pub enabled: bool,
+    /// Beam width in pixels.
+    pub thickness: f64,
+    /// Bloom around bright lines.
+    pub glow: bool,
+    /// Phosphor afterimages fading over a few frames.
+    pub persistence: bool,
+    /// The beam wobbling slightly off the ideal line.
+    pub jitter: bool,
+    pub scanlines: bool,
+    /// Barrel distortion like a curved tube.
+    pub curvature: bool,
+}
+impl Default for MonitorSettings {
+    fn default() -> MonitorSettings {
+        MonitorSettings {
+            enabled: false,
+            thickness: 1.5,
+            glow: true,
+            persistence: true,
+            jitter: true,
+            scanlines: false,
+            curvature: false,
+        }
+    }
+}
+
+/// How the fixed-size playfield is fitted to the window. Both keep its
+/// aspect ratio and fill the rest with black bars.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Scaling {
+    /// As large as fits.
+    #[default]
+    Stretched,
+    /// Whole multiples of the playfield size (or whole fractions when the
+    /// window is smaller), so pixels stay square and sharp.
+    PixelExact,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DisplaySettings {
+    /// Start in fullscreen; Alt+Enter toggles it while playing.
+    pub fullscreen: bool,
+    pub scaling: Scaling,
+}
+
+/// How several ships share a game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Mode {
+    /// Everyone shoots asteroids; bullets pass through the other ships.
+    #[default]
+    Coop,
+    /// Bullets hit the other ships too, for points.
+    Versus,
+}
+
+/// Rules for new games. They change how the simulation runs, so replays
+/// store them too.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rules {
+    /// Playfield size in screens per side; above 1 the camera follows the
+    /// ship and a radar shows the rest.
+    pub arena: u32,
+    /// Ships in the game, each with its own input, from 1 to `MAX_PLAYERS`.
+    pub players: usize,
+    pub mode: Mode,
+    /// Ships each player starts with.
+    pub lives: u32,
+    /// In co-op, all players draw their spare ships from one pool.
+    pub shared_lives: bool,
+}
+/// Most ships a game can have.
+pub const MAX_PLAYERS: usize = 4;
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            arena: 1,
+            players: 1,
+            mode: Mode::Coop,
+            lives: 1,
+            shared_lives: false,
+        }
+    }
+}
+impl Rules {
+    /// Size of the playfield these rules play on.
+    pub fn bounds(&self) -> Vec2 {
+        PLAYFIELD * self.arena as f64
+    }
+    /// Changes one rule by name. Returns `Ok(false)` for names that are
+    /// not rules.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        match key {
+            "arena" => {
+                self.arena = value
+                    .parse()
+                    .ok()
+                    .filter(|arena| (1..=8).contains(arena))
+                    .ok_or("arena must be between 1 and 8")?
+            }
+            "players" => {
+                self.players = value
+                    .parse()
+                    .ok()
+                    .filter(|players| (1..=MAX_PLAYERS).contains(players))
+                    .ok_or_else(|| format!("players must be between 1 and {}", MAX_PLAYERS))?
+            }
+            "mode" => {
+                self.mode = match value {
+                    "coop" => Mode::Coop,
+                    "versus" => Mode::Versus,
+                    _ => return Err("mode must be coop or versus".into()),
+                }
+            }
+            "lives" => {
+                self.lives = value
+                    .parse()
+                    .ok()
+                    .filter(|lives| (1..=99).contains(lives))
+                    .ok_or("lives must be between 1 and 99")?
+            }
+            "shared-lives" => self.shared_lives = flag(key, value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    /// Every rule as a name and value, as `set` reads them.
+    pub fn entries(&self) -> [(&'static str, String); 5] {
+        let mode = match self.mode {
+            Mode::Coop => "coop",
+            Mode::Versus => "versus",
+        };
+        [
+            ("arena", self.arena.to_string()),
+            ("players", self.players.to_string()),
+            ("mode", mode.to_string()),
+            ("lives", self.lives.to_string()),
+            ("shared-lives", self.shared_lives.to_string()),
+        ]
+    }
+}
+
+fn flag(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" => Ok(true),
+        "false" | "off" => Ok(false),
+        _ => Err(format!("{} must be true or false, not {}", key, value)),
+    }
+}
+
+/// Everything that can be set in the settings file. Missing keys keep their
+/// defaults, so an empty file is valid.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Settings {
+    pub rules: Rules,
+    pub display: DisplaySettings,
+    pub monitor: MonitorSettings,
+}
+impl Settings {
+    pub fn parse(text: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("expected key = value: {}", line))?;
+            settings.set(key.trim(), value.trim())?;
+        }
+        Ok(settings)
+    }
+    /// Changes one setting by name, as written in the file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if self.rules.set(key, value)? {
+            return Ok(());
+        }
+        let flag = |value: &str| flag(key, value);
+        let monitor = &mut self.monitor;
+        match key {
+            "fullscreen" => self.display.fullscreen = flag(value)?,
+            "scaling" => {
+                self.display.scaling = match value {
+                    "stretched" => Scaling::Stretched,
+                    "pixel-exact" => Scaling::PixelExact,
+                    _ => return Err("scaling must be stretched or pixel-exact".into()),
+                }
+            }
+            "monitor" => monitor.enabled = flag(value)?,
+            "monitor.thickness" => {
+                monitor.thickness = value
+                    .parse()
+                    .ok()
+                    .filter(|width| (0.5..=8.0).contains(width))
+                    .ok_or("monitor.thickness must be between 0.5 and 8")?
+            }
+            "monitor.glow" => monitor.glow = flag(value)?,
+            "monitor.persistence" => monitor.persistence = flag(value)?,
+            "monitor.jitter" => monitor.jitter = flag(value)?,
+            "monitor.scanlines" => monitor.scanlines = flag(value)?,
+            "monitor.curvature" => monitor.curvature = flag(value)?,
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+    /// Reads `path`, or returns the defaults when it does not exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Settings> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Settings::parse(&text)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(error),
+        }
+    }
+}
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (key, value) in self.rules.entries() {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+        let display = &self.display;
+        writeln!(f, "fullscreen = {}", display.fullscreen)?;
+        let scaling = match display.scaling {
+            Scaling::Stretched => "stretched",
+            Scaling::PixelExact => "pixel-exact",
+        };
+        writeln!(f, "scaling = {}", scaling)?;
+        let monitor = &self.monitor;
+        writeln!(f, "monitor = {}", monitor.enabled)?;
+        writeln!(f, "monitor.thickness = {}", monitor.thickness)?;
+        writeln!(f, "monitor.glow = {}", monitor.glow)?;
+        writeln!(f, "monitor.persistence = {}", monitor.persistence)?;
+        writeln!(f, "monitor.jitter = {}", monitor.jitter)?;
+        writeln!(f, "monitor.scanlines = {}", monitor.scanlines)?;
+        writeln!(f, "monitor.curvature = {}", monitor.curvature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write_back() {
+        let settings = Settings::parse(
+            "# look\nmonitor = on\n\nmonitor.thickness = 2.5\nmonitor.glow=false\nmode = versus\n",
+        )
+        .unwrap();
+        assert!(settings.monitor.enabled);
+        assert_eq!(settings.monitor.thickness, 2.5);
+        assert!(!settings.monitor.glow);
+        assert!(settings.monitor.persistence);
+        assert_eq!(settings.rules.mode, Mode::Versus);
+        assert_eq!(Settings::parse(&settings.to_string()).unwrap(), settings);
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert!(Settings::parse("monitor").is_err());
+        assert!(Settings::parse("monitor = maybe").is_err());
+        assert!(Settings::parse("monitor.thickness = 100").is_err());
+        assert!(Settings::parse("volume = 3").is_err());
+        assert!(Settings::parse("scaling = fuzzy").is_err());
+        assert!(Settings::parse("arena = 0").is_err());
+        assert!(Settings::parse("players = 5").is_err());
+        assert!(Settings::parse("mode = tag").is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! Ships, bullets and asteroids: every entity is a `Shape`, an outline in
+//! local space placed in the world by a position, rotation and scale.
+
+use crate::camera::Camera;
+use crate::input::ShipInput;
+use crate::math::{wrap_angle, Vec2};
+use crate::render::{Color, Renderer};
+use rand::Rng;
+use std::f64::consts::*;
+
+/// What a shape is; decides its outline and how it moves.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ShapeType {
+    /// Spin applied to the outline every frame, in radians.
+    Asteroid(f64),
+    Ship,
+    Bullet,
+}
+impl ShapeType {
+    /// The asteroid spin, or zero for anything else.
+    pub fn unwrap(&self) -> f64 {
+        match self {
+            ShapeType::Asteroid(s) => *s,
+            _ => 0.0,
+        }
+    }
+}
+/// A drawable, collidable entity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Shape {
+    pub pos: Vec2,
+    /// Heading in radians; also the facing of ships and bullets.
+    pub rot: f64,
+    /// Speed along the heading, as a fraction of what the kind allows.
+    pub s: f64,
+    /// Turn rate, from -1 to 1.
+    pub s_rot: f64,
+    /// Outline in local space, unit sized and facing +x. Never changes after
+    /// creation; `pos`, `rot + spin` and `scale` place it in the world.
+    pub outline: Vec<Vec2>,
+    /// Extra rotation of the outline, accumulated from the asteroid spin.
+    pub spin: f64,
+    pub color: Color,
+    /// Radius in pixels.
+    pub scale: f64,
+    pub kind: ShapeType,
+    /// Wraps around the playfield edges. Asteroids become bound once they
+    /// have fully drifted in.
+    pub bound: bool,
+    /// Index of the ship a ship or bullet belongs to.
+    pub owner: usize,
+}
+impl Shape {
+    pub fn new(
+        pos: Vec2,
+        rot: f64,
+        scale: f64,
+        color: Color,
+        shape: ShapeType,
+        rng: &mut impl Rng,
+    ) -> Shape {
+        let mut new = Shape {
+            pos,
+            rot,
+            s: 0.0,
+            s_rot: 0.0,
+            outline: Vec::new(),
+            spin: 0.0,
+            color,
+            scale,
+            kind: ShapeType::Ship,
+            bound: false,
+            owner: 0,
+        };
+        new.set_kind(shape, rng);
+        new
+    }
+    pub fn set_bound(&mut self) {
+        self.bound = true;
+    }
+    pub fn set_kind(&mut self, kind: ShapeType, rng: &mut impl Rng) {
+        self.kind = kind;
+        self.spin = 0.0;
+        self.outline = match self.kind {
+            ShapeType::Ship => vec![
+                Vec2::new(1.0, 0.0),
+                -Vec2::from_angle(0.53),
+                Vec2::new(-0.25, 0.0),
+                -Vec2::from_angle(-0.53),
+            ],
+            ShapeType::Bullet => vec![
+                Vec2::new(1.0, 0.0),
+                -Vec2::from_angle(-0.53),
+                -Vec2::from_angle(0.53),
+            ],
+            ShapeType::Asteroid(_) => {
+                let mut outline = Vec::new();
+                let mut angle: f64 = 0.0;
+                while angle < TAU {
+                    let distance = rng.gen_range(0.7..=1.0);
+                    outline.push(Vec2::from_angle(angle) * distance);
+                    angle += rng.gen_range(PI / 32.0..=PI / 8.0);
+                }
+                let spin = rng.gen_range(0.0..=PI / 64.0);
+                if rng.gen_range(0..=1) == 1 {
+                    self.kind = ShapeType::Asteroid(-spin);
+                } else {
+                    self.kind = ShapeType::Asteroid(spin);
+                }
+                outline
+            }
+        };
+    }
+    pub fn fire(&self, rng: &mut impl Rng) -> Shape {
+        let mut bullet = Shape::new(
+            self.pos + Vec2::from_angle(self.rot) * self.scale,
+            self.rot,
+            self.scale / 4.0,
+            self.color,
+            ShapeType::Bullet,
+            rng,
+        );
+        bullet.s = self.scale;
+        bullet.owner = self.owner;
+        bullet
+    }
+    /// Moves an asteroid along its heading and turns its outline.
+    pub fn tick(&mut self) {
+        self.pos += self.velocity();
+        self.spin = wrap_angle(self.spin + self.kind.unwrap());
+    }
+    /// Distance covered per frame, in the direction of travel.
+    pub fn velocity(&self) -> Vec2 {
+        let speed = match self.kind {
+            ShapeType::Asteroid(_) => self.scale / 16.0 * self.s,
+            ShapeType::Ship => self.scale / 4.0 * self.s,
+            ShapeType::Bullet => self.s,
+        };
+        Vec2::from_angle(self.rot) * speed
+    }
+    /// World space verticies, offset by `shift` for wrap-around copies.
+    pub fn verticies(&self, shift: Vec2) -> Vec<Vec2> {
+        let dir = Vec2::from_angle(self.rot + self.spin);
+        let origin = self.pos + shift;
+        self.outline
+            .iter()
+            .map(|p| origin + p.rotate_by(dir) * self.scale)
+            .collect()
+    }
+    /// Whether `point` lies inside the outline.
+    pub fn contains(&self, point: Vec2) -> bool {
+        if (point - self.pos).length_squared() > self.scale * self.scale {
+            return false;
+        }
+        let v = self.verticies(Vec2::ZERO);
+        let mut inside = false;
+        let mut j = v.len() - 1;
+        for i in 0..v.len() {
+            let (a, b) = (v[i], v[j]);
+            if (a.y > point.y) != (b.y > point.y) && (b - a).cross(point - a) * (b.y - a.y) > 0.0 {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+    pub fn draw_at(&self, shift: Vec2, r: &mut dyn Renderer) {
+        r.set_color(self.color);
+        r.polygon(&self.verticies(shift));
+    }
+    /// Draws every copy of the shape the camera can see.
+    pub fn draw(&self, bounds: Vec2, camera: &Camera, r: &mut dyn Renderer) {
+        for shift in self.copies(bounds, camera) {
+            self.draw_at(shift, r);
+        }
+    }
+    /// Keeps a bound shape inside the playfield by moving it to the other side.
+    pub fn wrap(&mut self, bounds: Vec2) {
+        if self.bound {
+            self.pos = self.pos.wrap(bounds);
+        }
+    }
+    /// Offsets of the copies of a bound shape that overlap the camera's view,
+    /// which on a torus can be several. Unbound shapes are only ever drawn where they are.
+    pub fn copies(&self, bounds: Vec2, camera: &Camera) -> Vec<Vec2> {
+        if !self.bound {
+            return vec![Vec2::ZERO];
+        }
+        let (min, max) = (camera.min(), camera.min() + camera.extent());
+        // Every whole number of bounds that moves the shape onto the view.
+        let range = |pos: f64, min: f64, max: f64, size: f64| {
+            let first = ((min - self.scale - pos) / size).ceil() as i64;
+            let last = ((max + self.scale - pos) / size).floor() as i64;
+            first..=last
+        };
+        let mut shifts = Vec::new();
+        for y in range(self.pos.y, min.y, max.y, bounds.y) {
+            for x in range(self.pos.x, min.x, max.x, bounds.x) {
+                shifts.push(Vec2::new(x as f64 * bounds.x, y as f64 * bounds.y));
+            }
+        }
+        // Unshifted first, so a world that fits on screen draws as it always has.
+        if let Some(i) = shifts.iter().position(|shift| *shift == Vec2::ZERO) {
+            shifts[..=i].rotate_right(1);
+        }
+        shifts
+    }
+    /// Turns and accelerates the ship from one frame of input.
+    pub fn direct(&mut self, input: &ShipInput) {
+        if input.left && self.s_rot > -1.0 && !input.right {
+            if self.s_rot > 0.0 {
+                self.s_rot -= 0.0625;
+            }
+            self.s_rot -= 0.0625;
+        } else if input.right && self.s_rot < 1.0 && !input.left {
+            if self.s_rot < 0.0 {
+                self.s_rot += 0.0625;
+            }
+            self.s_rot += 0.0625;
+        } else {
+            if self.s_rot < 0.0 {
+                self.s_rot += 0.0625;
+            }
+            if self.s_rot > 0.0 {
+                self.s_rot -= 0.0625;
+            }
+        }
+        if input.thrust && self.s < 1.0 && !input.reverse {
+            if self.s < 0.0 {
+                self.s += 0.03125;
+            }
+            self.s += 0.03125;
+        } else if input.reverse && self.s > -0.5 && !input.thrust {
+            if self.s > 0.0 {
+                self.s -= 0.03125;
+            }
+            self.s -= 0.03125;
+        } else if self.s > 0.0 {
+            self.s -= 0.03125;
+        } else if self.s < 0.0 {
+            self.s += 0.03125;
+        }
+        self.rot += self.s_rot * 0.1;
+        self.pos += self.velocity();
+    }
+    pub fn color(&mut self, r: u8, g: u8, b: u8) {
+        self.color = Color::rgb(r, g, b);
+    }
+}
+
+/// Largest asteroid radius.
+pub const MAX_ASTEROID_SCALE: f64 = 80.0;
+
+/// A new asteroid just outside the playfield, heading roughly for its middle.
+pub fn create_asteroid(
+    rng: &mut impl Rng,
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+) -> Shape {
+    let enter = rng.gen_range(0..=3);
+    let mut rpoint = Vec2::ZERO;
+    let mut rot = 0.0;
+    if enter == 0 {
+        rpoint = Vec2::new(
+            rng.gen_range(-x_max / 2.0..=x_min),
+            rng.gen_range(-y_max / 2.0..=y_min),
+        );
+        rot = ((x_max / 2.0 - rpoint.x) / (y_max / 2.0 - rpoint.y)).atan();
+    } else if enter == 1 {
+        rpoint = Vec2::new(
+            rng.gen_range(x_max..=x_max + x_max / 2.0),
+            rng.gen_range(-y_max / 2.0..=y_min),
+        );
+        rot = PI + ((x_max / 2.0 - rpoint.x) / (y_max / 2.0 - rpoint.y)).atan();
+    } else if enter == 2 {
+        rpoint = Vec2::new(
+            rng.gen_range(x_max..=x_max + x_max / 2.0),
+            rng.gen_range(y_max..=y_max + y_max / 2.0),
+        );
+        rot = PI + ((x_max / 2.0 - rpoint.x) / (y_max / 2.0 - rpoint.y)).atan();
+    } else if enter == 3 {
+        rpoint = Vec2::new(
+            rng.gen_range(-x_max / 2.0..=x_min),
+            rng.gen_range(y_max..=y_max + y_max / 2.0),
+        );
+        rot = ((x_max / 2.0 - rpoint.x) / (y_max / 2.0 - rpoint.y)).atan();
+    }
+    let scale = rng.gen_range(10.0..=MAX_ASTEROID_SCALE);
+    let mut asteroid = Shape::new(
+        rpoint,
+        rot,
+        scale,
+        Color::rgb(255, 255, 255),
+        ShapeType::Asteroid(0.0),
+        rng,
+    );
+    asteroid.s = rng.gen_range(0.25..=0.75);
+    asteroid
+}
+/// The two smaller asteroids a shot asteroid breaks into.
+pub fn split_asteroid(old: &Shape, rng: &mut impl Rng) -> [Shape; 2] {
+    let angle = rng.gen_range(PI / 4.0..=PI / 2.0);
+    let speed_diff = rng.gen_range(old.s * 0.25..=old.s * 0.75);
+    let scale_diff = rng.gen_range(old.scale * 0.40..=old.scale * 0.60);
+    let mut new1 = Shape::new(
+        old.pos,
+        old.rot + angle,
+        old.scale - scale_diff,
+        old.color,
+        ShapeType::Asteroid(0.0),
+        rng,
+    );
+    new1.s = rng.gen_range(old.s..=old.s * 2.0);
+    new1.s_rot = 2.0 * old.s - speed_diff;
+    new1.set_bound();
+    let mut new2 = Shape::new(
+        old.pos,
+        old.rot - angle,
+        scale_diff,
+        old.color,
+        ShapeType::Asteroid(0.0),
+        rng,
+    );
+    new2.s = old.s + speed_diff;
+    new2.s_rot = old.s_rot;
+    new2.set_bound();
+    [new1, new2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn radius(shape: &Shape, point: Vec2) -> f64 {
+        (point - shape.pos).length()
+    }
+
+    #[test]
+    fn outline_survives_thousands_of_frames() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut asteroid = create_asteroid(&mut rng, 0.0, 600.0, 0.0, 600.0);
+        asteroid.kind = ShapeType::Asteroid(PI / 64.0);
+        let outline = asteroid.outline.clone();
+        let before: Vec<f64> = asteroid
+            .verticies(Vec2::ZERO)
+            .iter()
+            .map(|p| radius(&asteroid, *p))
+            .collect();
+        for _ in 0..10_000 {
+            asteroid.tick();
+            asteroid.pos = asteroid.pos.wrap(Vec2::new(600.0, 600.0));
+        }
+        assert_eq!(asteroid.outline, outline);
+        let after = asteroid.verticies(Vec2::ZERO);
+        for (p, r) in after.iter().zip(before) {
+            assert!((radius(&asteroid, *p) - r).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn full_turn_returns_to_start() {
+        let mut asteroid = Shape::new(
+            Vec2::new(100.0, 100.0),
+            0.0,
+            40.0,
+            Color::rgb(255, 255, 255),
+            ShapeType::Asteroid(0.0),
+            &mut StdRng::seed_from_u64(2),
+        );
+        asteroid.kind = ShapeType::Asteroid(TAU / 1000.0);
+        let start = asteroid.verticies(Vec2::ZERO);
+        for _ in 0..5_000 {
+            asteroid.tick();
+            asteroid.pos = Vec2::new(100.0, 100.0);
+        }
+        let end = asteroid.verticies(Vec2::ZERO);
+        for (a, b) in start.iter().zip(end.iter()) {
+            assert!((*a - *b).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn outline_is_unit_sized() {
+        let asteroid = create_asteroid(&mut StdRng::seed_from_u64(3), 0.0, 600.0, 0.0, 600.0);
+        for p in &asteroid.outline {
+            let r = p.length();
+            assert!((0.7 - 1e-12..=1.0 + 1e-12).contains(&r));
+        }
+    }
+
+    #[test]
+    fn ship_nose_follows_rotation() {
+        let ship = Shape::new(
+            Vec2::new(50.0, 50.0),
+            PI / 2.0,
+            10.0,
+            Color::rgb(255, 255, 255),
+            ShapeType::Ship,
+            &mut StdRng::seed_from_u64(4),
+        );
+        let nose = ship.verticies(Vec2::ZERO)[0];
+        assert!((nose.x - 50.0).abs() < 1e-9);
+        assert!((nose.y - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contains_uses_outline() {
+        let asteroid = Shape::new(
+            Vec2::ZERO,
+            0.0,
+            30.0,
+            Color::rgb(255, 255, 255),
+            ShapeType::Asteroid(0.0),
+            &mut StdRng::seed_from_u64(5),
+        );
+        assert!(asteroid.contains(Vec2::ZERO));
+        assert!(asteroid.contains(Vec2::new(20.0, 0.0)));
+        assert!(!asteroid.contains(Vec2::new(31.0, 0.0)));
+    }
+
+    #[test]
+    fn copies_cover_corners() {
+        let bounds = Vec2::new(600.0, 600.0);
+        let mut ship = Shape::new(
+            Vec2::new(5.0, 595.0),
+            0.0,
+            10.0,
+            Color::rgb(255, 255, 255),
+            ShapeType::Ship,
+            &mut StdRng::seed_from_u64(6),
+        );
+        let camera = Camera::new(bounds, bounds);
+        assert_eq!(ship.copies(bounds, &camera), vec![Vec2::ZERO]);
+        ship.set_bound();
+        let shifts = ship.copies(bounds, &camera);
+        assert_eq!(shifts.len(), 4);
+        assert!(shifts.contains(&Vec2::new(600.0, -600.0)));
+        // Far from the edges of a big world, only the copy in view is drawn.
+        let bounds = Vec2::new(1800.0, 1800.0);
+        let mut camera = Camera::new(bounds, Vec2::new(600.0, 600.0));
+        camera.center = Vec2::new(1700.0, 900.0);
+        ship.pos = Vec2::new(100.0, 900.0);
+        assert_eq!(ship.copies(bounds, &camera), vec![Vec2::new(1800.0, 0.0)]);
+    }
+}
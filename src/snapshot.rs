@@ -0,0 +1,377 @@
+//! Whole worlds as bytes, for spectators who join a game late. A decoded
+//! snapshot steps on exactly like the world it was taken from.
+
+use crate::arena::Arena;
+use crate::camera::Camera;
+use crate::math::Vec2;
+use crate::render::Color;
+use crate::settings::Rules;
+use crate::shape::{Shape, ShapeType};
+use crate::world::{Ship, World};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+const MAGIC: &[u8; 4] = b"FXW1";
+
+/// The state of `world` between two frames.
+pub fn encode(world: &World) -> Vec<u8> {
+    let mut out = Writer(MAGIC.to_vec());
+    out.u64(world.seed);
+    out.u64(world.frame);
+    let entries = world.rules.entries();
+    out.u32(entries.len() as u32);
+    for (key, value) in &entries {
+        out.text(key);
+        out.text(value);
+    }
+    out.u8(world.fire_period);
+    out.u8(world.god as u8);
+    out.u32(world.spare);
+    out.vec2(world.bounds);
+    let camera = &world.camera;
+    out.vec2(camera.center);
+    out.f64(camera.zoom);
+    out.vec2(camera.view);
+    out.u8(camera.follows as u8);
+    out.0.extend_from_slice(&world.rng.get_seed());
+    out.u64(world.rng.get_stream());
+    out.0
+        .extend_from_slice(&world.rng.get_word_pos().to_le_bytes());
+    out.u32(world.ships.len() as u32);
+    for ship in &world.ships {
+        out.shape(&ship.shape);
+        out.u8(ship.alive as u8);
+        out.u32(ship.score);
+        out.u32(ship.spare);
+        out.u8(ship.fire_delay);
+        out.color(ship.color);
+        out.u32(ship.respawn);
+        out.u32(ship.shield);
+        out.vec2(ship.spawn);
+        out.u32(ship.hyperspace);
+    }
+    out.arena(&world.asteroids);
+    out.arena(&world.bullets);
+    out.0
+}
+
+/// The world `encode` saved, or `None` if `bytes` are not one.
+pub fn decode(bytes: &[u8]) -> Option<World> {
+    let mut input = Reader(bytes.strip_prefix(MAGIC)?);
+    let seed = input.u64()?;
+    let frame = input.u64()?;
+    let mut rules = Rules::default();
+    for _ in 0..input.u32()? {
+        let (key, value) = (input.text()?, input.text()?);
+        rules.set(&key, &value).ok().filter(|known| *known)?;
+    }
+    let mut world = World::with_rules(rules, seed);
+    world.frame = frame;
+    world.fire_period = input.u8()?;
+    world.god = input.u8()? != 0;
+    world.spare = input.u32()?;
+    // The spatial grid was sized from the rules, so the bounds must match.
+    if input.vec2()? != world.bounds {
+        return None;
+    }
+    world.camera = Camera {
+        center: input.vec2()?,
+        zoom: input.f64()?,
+        view: input.vec2()?,
+        follows: input.u8()? != 0,
+    };
+    let mut rng = ChaCha12Rng::from_seed(input.bytes::<32>()?);
+    rng.set_stream(input.u64()?);
+    rng.set_word_pos(u128::from_le_bytes(input.bytes()?));
+    world.rng = rng;
+    world.ships = (0..input.u32()?)
+        .map(|_| {
+            Some(Ship {
+                shape: input.shape()?,
+                alive: input.u8()? != 0,
+                score: input.u32()?,
+                spare: input.u32()?,
+                fire_delay: input.u8()?,
+                color: input.color()?,
+                respawn: input.u32()?,
+                shield: input.u32()?,
+                spawn: input.vec2()?,
+                hyperspace: input.u32()?,
+            })
+        })
+        .collect::<Option<_>>()?;
+    world.asteroids = input.arena()?;
+    world.bullets = input.arena()?;
+    // Stepping looks ships up by owner and divides by the time left until a
+    // ship fires again, so a sender's bad owner, ship count or fire timing
+    // would panic rather than play.
+    let ships = world.ships.len();
+    let fits = (world.ships.iter().map(|ship| &ship.shape))
+        .chain(world.asteroids.values())
+        .chain(world.bullets.values())
+        .all(|shape| shape.owner < ships);
+    let period = world.fire_period;
+    let reloads = world.ships.iter().all(|ship| ship.fire_delay < period);
+    if ships != world.rules.players || !fits || !reloads {
+        return None;
+    }
+    input.0.is_empty().then_some(world)
+}
+
+struct Writer(Vec<u8>);
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+    fn vec2(&mut self, value: Vec2) {
+        self.f64(value.x);
+        self.f64(value.y);
+    }
+    fn color(&mut self, color: Color) {
+        self.0
+            .extend_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+    fn text(&mut self, text: &str) {
+        self.u32(text.len() as u32);
+        self.0.extend_from_slice(text.as_bytes());
+    }
+    fn shape(&mut self, shape: &Shape) {
+        self.vec2(shape.pos);
+        self.f64(shape.rot);
+        self.f64(shape.s);
+        self.f64(shape.s_rot);
+        self.u32(shape.outline.len() as u32);
+        for point in &shape.outline {
+            self.vec2(*point);
+        }
+        self.f64(shape.spin);
+        self.color(shape.color);
+        self.f64(shape.scale);
+        match shape.kind {
+            ShapeType::Asteroid(spin) => {
+                self.u8(0);
+                self.f64(spin);
+            }
+            ShapeType::Ship => self.u8(1),
+            ShapeType::Bullet => self.u8(2),
+        }
+        self.u8(shape.bound as u8);
+        self.u32(shape.owner as u32);
+    }
+    // Free slots too: where the next asteroid lands in the arena decides the
+    // order things collide in.
+    fn arena(&mut self, arena: &Arena<Shape>) {
+        let (slots, free) = arena.slots();
+        self.u32(slots.len() as u32);
+        for (generation, value) in slots {
+            self.u32(generation);
+            match value {
+                Some(shape) => {
+                    self.u8(1);
+                    self.shape(shape);
+                }
+                None => self.u8(0),
+            }
+        }
+        self.u32(free.len() as u32);
+        for index in free {
+            self.u32(*index);
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (bytes, rest) = self.0.split_first_chunk()?;
+        self.0 = rest;
+        Some(*bytes)
+    }
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes::<1>()?[0])
+    }
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes()?))
+    }
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes()?))
+    }
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_bits(self.u64()?))
+    }
+    fn vec2(&mut self) -> Option<Vec2> {
+        Some(Vec2::new(self.f64()?, self.f64()?))
+    }
+    fn color(&mut self) -> Option<Color> {
+        let [r, g, b, a] = self.bytes()?;
+        Some(Color { r, g, b, a })
+    }
+    fn text(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        let text = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        String::from_utf8(text.to_vec()).ok()
+    }
+    // Counts come from the sender, so they are checked against what is left
+    // before anything is allocated for them.
+    fn count(&mut self, min_size: usize) -> Option<usize> {
+        let count = self.u32()? as usize;
+        (count * min_size <= self.0.len()).then_some(count)
+    }
+    fn shape(&mut self) -> Option<Shape> {
+        let pos = self.vec2()?;
+        let rot = self.f64()?;
+        let s = self.f64()?;
+        let s_rot = self.f64()?;
+        // Hit tests walk the outline's edges, which needs at least a point.
+        let outline = (0..self.count(16).filter(|count| *count > 0)?)
+            .map(|_| self.vec2())
+            .collect::<Option<_>>()?;
+        Some(Shape {
+            pos,
+            rot,
+            s,
+            s_rot,
+            outline,
+            spin: self.f64()?,
+            color: self.color()?,
+            scale: self.f64()?,
+            kind: match self.u8()? {
+                0 => ShapeType::Asteroid(self.f64()?),
+                1 => ShapeType::Ship,
+                2 => ShapeType::Bullet,
+                _ => return None,
+            },
+            bound: self.u8()? != 0,
+            owner: self.u32()? as usize,
+        })
+    }
+    fn arena(&mut self) -> Option<Arena<Shape>> {
+        let slots = (0..self.count(5)?)
+            .map(|_| {
+                let generation = self.u32()?;
+                let value = match self.u8()? {
+                    0 => None,
+                    _ => Some(self.shape()?),
+                };
+                Some((generation, value))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        // Inserting fills free slots without looking, so each must be empty
+        // and listed once.
+        let mut listed = vec![false; slots.len()];
+        let free = (0..self.count(4)?)
+            .map(|_| {
+                let index = self.u32()?;
+                let slot = index as usize;
+                let empty = slots.get(slot)?.1.is_none() && !listed[slot];
+                listed[slot] = true;
+                empty.then_some(index)
+            })
+            .collect::<Option<_>>()?;
+        Some(Arena::from_slots(slots, free))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::ShipInput;
+    use crate::settings::Mode;
+
+    #[test]
+    fn snapshots_play_on_the_same() {
+        let rules = Rules {
+            players: 2,
+            mode: Mode::Versus,
+            arena: 2,
+            ..Rules::default()
+        };
+        let mut world = World::with_rules(rules, 5);
+        let fire = ShipInput {
+            fire: true,
+            left: true,
+            ..ShipInput::default()
+        };
+        for _ in 0..500 {
+            world.step(&[fire, ShipInput::default()]);
+        }
+        let bytes = encode(&world);
+        let mut copy = decode(&bytes).unwrap();
+        assert_eq!(copy.checksum(), world.checksum());
+        assert_eq!(encode(&copy), bytes);
+        for _ in 0..500 {
+            world.step(&[fire, fire]);
+            copy.step(&[fire, fire]);
+        }
+        assert_eq!(copy.checksum(), world.checksum());
+        assert!(decode(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn owners_and_ship_counts_must_fit() {
+        let mut world = World::with_rules(Rules::default(), 5);
+        let fire = ShipInput {
+            fire: true,
+            ..ShipInput::default()
+        };
+        while world.bullets.is_empty() {
+            world.step(&[fire]);
+        }
+        assert!(decode(&encode(&world)).is_some());
+        let mut corrupt = decode(&encode(&world)).unwrap();
+        for bullet in corrupt.bullets.values_mut() {
+            bullet.owner = 1;
+        }
+        assert!(decode(&encode(&corrupt)).is_none());
+        let mut corrupt = decode(&encode(&world)).unwrap();
+        corrupt.ships.push(corrupt.ships[0].clone());
+        assert!(decode(&encode(&corrupt)).is_none());
+    }
+
+    #[test]
+    fn outlines_free_slots_and_bounds_must_fit() {
+        let mut world = World::with_rules(Rules::default(), 5);
+        let first = world.spawn_asteroid();
+        world.spawn_asteroid();
+        world.asteroids.remove(first);
+        assert!(decode(&encode(&world)).is_some());
+        let mut corrupt = decode(&encode(&world)).unwrap();
+        corrupt.ships[0].shape.outline.clear();
+        assert!(decode(&encode(&corrupt)).is_none());
+        // The one free slot listed twice, then an occupied slot listed.
+        let (slots, free) = world.asteroids.slots();
+        let slots: Vec<_> = (slots.into_iter())
+            .map(|(generation, value)| (generation, value.cloned()))
+            .collect();
+        let occupied = slots.iter().position(|(_, value)| value.is_some()).unwrap();
+        for free in [[free[0], free[0]], [free[0], occupied as u32]] {
+            let mut corrupt = decode(&encode(&world)).unwrap();
+            corrupt.asteroids = Arena::from_slots(slots.clone(), free.to_vec());
+            assert!(decode(&encode(&corrupt)).is_none());
+        }
+        let mut corrupt = decode(&encode(&world)).unwrap();
+        corrupt.bounds *= 2.0;
+        assert!(decode(&encode(&corrupt)).is_none());
+    }
+
+    #[test]
+    fn fire_timing_must_fit() {
+        let world = World::with_rules(Rules::default(), 5);
+        let mut corrupt = decode(&encode(&world)).unwrap();
+        corrupt.fire_period = 0;
+        assert!(decode(&encode(&corrupt)).is_none());
+        let mut corrupt = decode(&encode(&world)).unwrap();
+        corrupt.ships[0].fire_delay = corrupt.fire_period;
+        assert!(decode(&encode(&corrupt)).is_none());
+    }
+}
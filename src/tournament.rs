@@ -0,0 +1,209 @@
+//! Bots playing each other for a rating. A match is one seeded two-player
+//! game, won by the ship with more points when both are out or time is up;
+//! Elo ratings carry over from tournament to tournament in a text file.
+
+use crate::control::{Observation, ShipController};
+use crate::settings::Rules;
+use crate::world::World;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+const HEADER: &str = "foxtroids ratings 1";
+/// Where a new entrant's rating starts.
+pub const START: f64 = 1500.0;
+/// How far one match can move a rating.
+pub const K: f64 = 32.0;
+
+/// The chance, by Elo, that a player rated `rating` beats one rated `other`,
+/// draws counting half.
+pub fn expected(rating: f64, other: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((other - rating) / 400.0))
+}
+
+/// One game of two ships under `rules`, ending when both are out or after
+/// `frames` frames. Answers the points each scored.
+pub fn play(
+    rules: &Rules,
+    seed: u64,
+    frames: u64,
+    mut controllers: [&mut dyn ShipController; 2],
+) -> [u32; 2] {
+    let rules = Rules {
+        players: 2,
+        ..rules.clone()
+    };
+    let mut world = World::with_rules(rules, seed);
+    while !world.over() && world.frame < frames {
+        let inputs: Vec<_> = controllers
+            .iter_mut()
+            .enumerate()
+            .map(|(ship, controller)| {
+                controller.command(&Observation {
+                    world: &world,
+                    ship,
+                })
+            })
+            .collect();
+        world.step(&inputs);
+    }
+    for (ship, controller) in controllers.iter_mut().enumerate() {
+        controller.finish(&Observation {
+            world: &world,
+            ship,
+        });
+    }
+    [world.ships[0].score, world.ships[1].score]
+}
+
+/// One entrant's standing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rating {
+    /// A single word, not starting with `#`.
+    pub name: String,
+    pub rating: f64,
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Everyone who ever played, best first once `rank`ed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ratings(pub Vec<Rating>);
+impl Ratings {
+    /// `name`'s standing, starting it at `START` if it has none yet.
+    pub fn get(&mut self, name: &str) -> &mut Rating {
+        let i = match self.0.iter().position(|rating| rating.name == name) {
+            Some(i) => i,
+            None => {
+                self.0.push(Rating {
+                    name: name.into(),
+                    rating: START,
+                    games: 0,
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                });
+                self.0.len() - 1
+            }
+        };
+        &mut self.0[i]
+    }
+    /// Rates a match between `a` and `b` whose points were `points`, moving
+    /// each by up to `k`.
+    pub fn record(&mut self, a: &str, b: &str, points: [u32; 2], k: f64) {
+        let ratings = [self.get(a).rating, self.get(b).rating];
+        for (side, name) in [a, b].into_iter().enumerate() {
+            let rating = self.get(name);
+            let score = match points[side].cmp(&points[1 - side]) {
+                Ordering::Greater => {
+                    rating.wins += 1;
+                    1.0
+                }
+                Ordering::Equal => {
+                    rating.draws += 1;
+                    0.5
+                }
+                Ordering::Less => {
+                    rating.losses += 1;
+                    0.0
+                }
+            };
+            rating.rating += k * (score - expected(ratings[side], ratings[1 - side]));
+            rating.games += 1;
+        }
+    }
+    /// Sorts best first.
+    pub fn rank(&mut self) {
+        self.0.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+    }
+    pub fn parse(text: &str) -> Result<Ratings, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        if lines.next() != Some(HEADER) {
+            return Err(format!("missing \"{}\" header", HEADER));
+        }
+        let bad = |line: &str| format!("bad rating: {}", line);
+        lines
+            .map(|line| {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let [name, rating, games, wins, draws, losses] = words[..] else {
+                    return Err(bad(line));
+                };
+                let count = |text: &str| text.parse().map_err(|_| bad(line));
+                Ok(Rating {
+                    name: name.into(),
+                    rating: (rating.parse().ok())
+                        .filter(|rating: &f64| rating.is_finite())
+                        .ok_or_else(|| bad(line))?,
+                    games: count(games)?,
+                    wins: count(wins)?,
+                    draws: count(draws)?,
+                    losses: count(losses)?,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Ratings)
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Ratings> {
+        Ratings::parse(&std::fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, self.to_string())?;
+        std::fs::rename(partial, path)
+    }
+}
+impl fmt::Display for Ratings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "# name rating games wins draws losses")?;
+        for rating in &self.0 {
+            writeln!(
+                f,
+                "{} {:.1} {} {} {} {}",
+                rating.name, rating.rating, rating.games, rating.wins, rating.draws, rating.losses
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Pilot;
+    use crate::control::Idle;
+
+    #[test]
+    fn pilots_beat_idlers_and_ratings_round_trip() {
+        let points = play(&Rules::default(), 3, 60 * 60, [&mut Pilot, &mut Idle]);
+        assert!(points[0] > 0 && points[1] == 0);
+        let mut ratings = Ratings::default();
+        ratings.record("pilot", "idle", points, K);
+        ratings.record("idle", "pilot", [5, 5], K);
+        ratings.rank();
+        let [pilot, idle] = &ratings.0[..] else {
+            panic!("{:?}", ratings);
+        };
+        assert_eq!(
+            (pilot.name.as_str(), pilot.wins, pilot.draws),
+            ("pilot", 1, 1)
+        );
+        assert_eq!((idle.games, idle.losses), (2, 1));
+        // Ratings only move between the two, and the draw moved them closer.
+        assert!((pilot.rating + idle.rating - 2.0 * START).abs() < 1e-9);
+        assert!(pilot.rating > START && pilot.rating < START + K / 2.0);
+        let text = ratings.to_string();
+        assert_eq!(Ratings::parse(&text).unwrap().to_string(), text);
+        assert!(Ratings::parse("foxtroids ratings 1\npilot 1500 1").is_err());
+        assert!(Ratings::parse("foxtroids ratings 1\npilot NaN 1 1 0 0").is_err());
+        assert!(Ratings::parse("foxtroids ratings 1\npilot inf 1 1 0 0").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! The terminal frontend: the game drawn in Braille characters and played
+//! with the keyboard, e.g. over SSH. It runs the same 60 Hz simulation as the
+//! SDL window.
+
+use crate::debug::DebugOverlay;
+use crate::input::ShipInput;
+use crate::math::Vec2;
+use crate::render::terminal::Terminal;
+use crate::render::{Color, Renderer};
+use crate::replay::Session;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, terminal};
+use std::io::{self, stdin, stdout, IsTerminal, Stdout};
+use std::time::{Duration, Instant};
+
+// Frames a key stays held after a press when the terminal cannot report
+// releases; longer than the usual delay before key repeat starts.
+const HOLD: u32 = 30;
+
+/// Turns key presses into held buttons for two players: WASD, Space and E
+/// for hyperspace, and the arrows, Enter and Backspace. Most terminals only
+/// send presses and auto-repeats, so without release events a key counts as
+/// held for `HOLD` frames after its last press.
+#[derive(Default)]
+pub struct HeldKeys {
+    // Frames left for left, right, thrust, reverse, fire and hyperspace, per
+    // player.
+    frames: [[u32; 6]; 2],
+}
+impl HeldKeys {
+    fn button(code: KeyCode) -> Option<(usize, usize)> {
+        match code {
+            KeyCode::Char('a') => Some((0, 0)),
+            KeyCode::Char('d') => Some((0, 1)),
+            KeyCode::Char('w') => Some((0, 2)),
+            KeyCode::Char('s') => Some((0, 3)),
+            KeyCode::Char(' ') => Some((0, 4)),
+            KeyCode::Char('e') => Some((0, 5)),
+            KeyCode::Left => Some((1, 0)),
+            KeyCode::Right => Some((1, 1)),
+            KeyCode::Up => Some((1, 2)),
+            KeyCode::Down => Some((1, 3)),
+            KeyCode::Enter => Some((1, 4)),
+            KeyCode::Backspace => Some((1, 5)),
+            _ => None,
+        }
+    }
+    /// `releases` says whether the terminal reports key releases.
+    pub fn key(&mut self, key: KeyEvent, releases: bool) {
+        if let Some((player, button)) = HeldKeys::button(key.code) {
+            self.frames[player][button] = match key.kind {
+                KeyEventKind::Release => 0,
+                _ if releases => u32::MAX,
+                _ => HOLD,
+            };
+        }
+    }
+    /// The buttons held this frame by each of `players`; counts the hold
+    /// timers down. A single player has both sets of keys.
+    pub fn tick(&mut self, players: usize) -> Vec<ShipInput> {
+        let mut inputs: Vec<ShipInput> = self
+            .frames
+            .iter()
+            .map(|frames| ShipInput::from_buttons(frames.map(|frames| frames > 0)))
+            .collect();
+        for frames in self.frames.iter_mut().flatten() {
+            if *frames != u32::MAX {
+                *frames = frames.saturating_sub(1);
+            }
+        }
+        if players == 1 {
+            inputs[0] = inputs[0] | inputs[1];
+        }
+        inputs.resize(players, ShipInput::default());
+        inputs
+    }
+}
+
+// Puts the terminal back the way it was, even when the game panics.
+struct RawMode {
+    releases: bool,
+}
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        if !stdin().is_terminal() || !stdout().is_terminal() {
+            return Err(io::Error::other("stdin and stdout must be a terminal"));
+        }
+        terminal::enable_raw_mode()?;
+        let mut raw = RawMode { releases: false };
+        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+            raw.releases = true;
+        }
+        Ok(raw)
+    }
+}
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// The largest square playfield that fits the terminal; Braille dots are
+// about as tall as they are wide.
+fn fit_screen(view: Vec2) -> io::Result<Terminal<Stdout>> {
+    let (cols, rows) = terminal::size()?;
+    let side = (cols as usize * 2).min(rows as usize * 4);
+    Ok(Terminal::braille(stdout(), side / 2, side / 4, view))
+}
+
+// Raw mode swallows Ctrl+C, so it quits along with `q` and Escape.
+fn quits(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => true,
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+/// Plays in the terminal until `q`, Escape or Ctrl+C is pressed. Fails
+/// without a terminal to play in. The replay is saved however play ends.
+pub fn run(mut session: Session) -> io::Result<()> {
+    let played = play(&mut session);
+    if let Err(error) = session.save() {
+        eprintln!("could not save the replay: {}", error);
+    }
+    played
+}
+
+fn play(session: &mut Session) -> io::Result<()> {
+    let raw = RawMode::enter()?;
+    let mut world = session.world();
+    let mut screen = fit_screen(world.camera.view)?;
+    let mut keys = HeldKeys::default();
+    let mut overlay = DebugOverlay::new();
+    let mut last_start = Instant::now();
+    let frame_dur = Duration::new(0, 1_000_000_000u32 / 60);
+
+    'running: loop {
+        let start = Instant::now();
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) if quits(&key) => break 'running,
+                Event::Key(key) => match key.code {
+                    KeyCode::F(3) if key.kind == KeyEventKind::Press => overlay.toggle(),
+                    _ => keys.key(key, raw.releases),
+                },
+                Event::Resize(..) => screen = fit_screen(world.camera.view)?,
+                _ => {}
+            }
+        }
+        let inputs = session.input(&world, &keys.tick(session.players()));
+        world.step(&inputs);
+
+        screen.clear(Color::BLACK);
+        world.draw(&mut screen);
+        overlay.draw(&world, &mut screen);
+        screen.present();
+        screen.take_error()?;
+
+        let delta_time = start.elapsed();
+        overlay.record(delta_time, start - last_start);
+        last_start = start;
+        if delta_time <= frame_dur {
+            std::thread::sleep(frame_dur - delta_time);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind)
+    }
+
+    #[test]
+    fn presses_hold_for_a_while_without_releases() {
+        let mut keys = HeldKeys::default();
+        keys.key(key(KeyCode::Char('w'), KeyEventKind::Press), false);
+        for _ in 0..HOLD {
+            assert!(keys.tick(1)[0].thrust);
+        }
+        assert_eq!(keys.tick(1), [ShipInput::default()]);
+    }
+
+    #[test]
+    fn releases_end_the_hold() {
+        let mut keys = HeldKeys::default();
+        keys.key(key(KeyCode::Left, KeyEventKind::Press), true);
+        keys.key(key(KeyCode::Char(' '), KeyEventKind::Press), true);
+        for _ in 0..HOLD * 2 {
+            assert!(keys.tick(1)[0].left);
+        }
+        keys.key(key(KeyCode::Left, KeyEventKind::Release), true);
+        let input = keys.tick(1)[0];
+        assert!(!input.left && input.fire);
+        // With two players the arrows belong to the second.
+        keys.key(key(KeyCode::Up, KeyEventKind::Press), true);
+        let inputs = keys.tick(2);
+        assert!(!inputs[0].thrust && inputs[1].thrust);
+    }
+
+    #[test]
+    fn ctrl_c_quits() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(quits(&ctrl_c));
+        assert!(!quits(&key(KeyCode::Char('c'), KeyEventKind::Press)));
+        assert!(quits(&key(KeyCode::Esc, KeyEventKind::Press)));
+    }
+}
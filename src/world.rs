@@ -0,0 +1,510 @@
+//! The game state and the rules that advance it one frame at a time.
+
+use crate::arena::{Arena, Handle};
+use crate::camera::Camera;
+use crate::font::draw_text;
+use crate::input::ShipInput;
+use crate::math::Vec2;
+use crate::physics::{collide, destroy, SpatialGrid};
+use crate::render::{Color, Renderer};
+use crate::settings::{Mode, Rules, MAX_PLAYERS};
+use crate::shape::{create_asteroid, Shape, ShapeType, MAX_ASTEROID_SCALE};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::f64::consts::*;
+
+/// Size of the standard playfield, in pixels.
+pub const PLAYFIELD: Vec2 = Vec2 { x: 600.0, y: 600.0 };
+// Largest asteroid radius, so a grid cell never needs more than its neighbours.
+const GRID_CELL: f64 = MAX_ASTEROID_SCALE;
+// Side of the radar in the bottom right corner, in screen units.
+const RADAR: f64 = 120.0;
+const RADAR_BACK: Color = Color::rgba(0, 30, 0, 180);
+const RADAR_EDGE: Color = Color::rgb(0, 160, 0);
+const RADAR_ASTEROID: Color = Color::rgb(150, 150, 150);
+
+// Colours of the ships, by player.
+const SHIP_COLORS: [Color; MAX_PLAYERS] = [
+    Color::WHITE,
+    Color::rgb(90, 200, 255),
+    Color::rgb(255, 220, 60),
+    Color::rgb(120, 255, 120),
+];
+// Distance between the ships' starting positions.
+const SHIP_SPACING: f64 = 80.0;
+// Frames before a lost ship comes back, and then how long it cannot be hit.
+const RESPAWN: u32 = 120;
+const SHIELD: u32 = 120;
+// Points for shooting down another ship in versus.
+const KILL_POINTS: u32 = 100;
+/// Frames the hyperspace drive needs to recharge after a jump.
+pub const HYPERSPACE_RECHARGE: u32 = 180;
+
+/// One player's ship and how that player is doing.
+#[derive(Clone)]
+pub struct Ship {
+    pub shape: Shape,
+    /// False while the ship is destroyed.
+    pub alive: bool,
+    pub score: u32,
+    /// Ships left after this one, unless the team shares `World::spare`.
+    pub spare: u32,
+    /// Frames since the last shot, counting up to `fire_period`.
+    pub fire_delay: u8,
+    /// The player's colour, which the ship returns to after firing.
+    pub color: Color,
+    /// Frames until a destroyed ship comes back; zero once it is out.
+    pub respawn: u32,
+    /// Frames a returned ship stays untouchable, blinking.
+    pub shield: u32,
+    /// Where the ship starts and comes back.
+    pub spawn: Vec2,
+    /// Frames until the hyperspace drive can jump again.
+    pub hyperspace: u32,
+}
+impl Ship {
+    fn new(player: usize, spawn: Vec2, spare: u32, rng: &mut ChaCha12Rng) -> Ship {
+        let color = SHIP_COLORS[player];
+        let mut shape = Shape::new(spawn, -PI / 2.0, 10.0, color, ShapeType::Ship, rng);
+        shape.set_bound();
+        shape.owner = player;
+        Ship {
+            shape,
+            alive: true,
+            score: 0,
+            spare,
+            fire_delay: 0,
+            color,
+            respawn: 0,
+            shield: 0,
+            spawn,
+            hyperspace: 0,
+        }
+    }
+    fn revive(&mut self) {
+        self.shape.pos = self.spawn;
+        self.shape.rot = -PI / 2.0;
+        self.shape.s = 0.0;
+        self.shape.s_rot = 0.0;
+        self.shape.color = self.color;
+        self.fire_delay = 0;
+        self.alive = true;
+        self.shield = SHIELD;
+    }
+    // The firing flash, from orange back to the ship's colour.
+    fn flash(&mut self, r: u8, g: u8, b: u8) {
+        let tint = |c: u8, base: u8| (c as u16 * base as u16 / 255) as u8;
+        let base = self.color;
+        self.shape
+            .color(tint(r, base.r), tint(g, base.g), tint(b, base.b));
+    }
+    /// Whether the ship is drawn this frame; it blinks while shielded.
+    pub fn visible(&self) -> bool {
+        self.alive && (self.shield / 4).is_multiple_of(2)
+    }
+}
+
+/// Everything that makes up one game: the ships, the entities around them
+/// and the seeded random generator that drives them. Cloning it takes a
+/// snapshot that can be stepped on its own.
+#[derive(Clone)]
+pub struct World {
+    /// One per player, in player order.
+    pub ships: Vec<Ship>,
+    pub bullets: Arena<Shape>,
+    pub asteroids: Arena<Shape>,
+    /// Bullets binned by position, rebuilt every frame.
+    pub grid: SpatialGrid,
+    /// Frames between shots.
+    pub fire_period: u8,
+    /// Asteroids and bullets pass through the ships.
+    pub god: bool,
+    pub rules: Rules,
+    /// The team's spare ships when `rules.shared_lives` is on.
+    pub spare: u32,
+    /// Size of the playfield; everything wraps at its edges.
+    pub bounds: Vec2,
+    /// Which part of the playfield is on screen; it follows the first ship
+    /// when the playfield is bigger than `PLAYFIELD`.
+    pub camera: Camera,
+    pub seed: u64,
+    /// The algorithm behind `StdRng`, named so snapshots can save its state.
+    pub rng: ChaCha12Rng,
+    /// Frames simulated so far.
+    pub frame: u64,
+}
+impl World {
+    /// A fresh one player game on a `bounds` sized playfield; equal seeds
+    /// give equal games for equal input.
+    pub fn new(bounds: Vec2, seed: u64) -> World {
+        World::create(bounds, seed, Rules::default())
+    }
+    /// A fresh game played by `rules`, on the playfield they ask for.
+    pub fn with_rules(rules: Rules, seed: u64) -> World {
+        World::create(rules.bounds(), seed, rules)
+    }
+    fn create(bounds: Vec2, seed: u64, rules: Rules) -> World {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let shared = rules.shared_lives && rules.mode == Mode::Coop;
+        let spare = rules.lives - 1;
+        let ships = (0..rules.players)
+            .map(|i| {
+                let offset = (i as f64 - (rules.players - 1) as f64 / 2.0) * SHIP_SPACING;
+                let spawn = bounds / 2.0 + Vec2::new(offset, 0.0);
+                Ship::new(i, spawn, if shared { 0 } else { spare }, &mut rng)
+            })
+            .collect();
+        World {
+            ships,
+            bullets: Arena::new(),
+            asteroids: Arena::new(),
+            grid: SpatialGrid::new(bounds, GRID_CELL),
+            fire_period: 15,
+            god: false,
+            spare: if shared {
+                spare * rules.players as u32
+            } else {
+                0
+            },
+            rules,
+            bounds,
+            camera: Camera::new(bounds, PLAYFIELD),
+            seed,
+            rng,
+            frame: 0,
+        }
+    }
+    /// Total score of all players.
+    pub fn score(&self) -> u32 {
+        self.ships.iter().map(|ship| ship.score).sum()
+    }
+    /// A hash of the simulated state, equal on every machine that ran the
+    /// same game; netplay compares them to catch desyncs. The camera is left
+    /// out as it only affects drawing.
+    pub fn checksum(&self) -> u64 {
+        let mut hash = Fnv::default();
+        hash.add(self.frame);
+        let add_shape = |hash: &mut Fnv, shape: &Shape| {
+            for value in [
+                shape.pos.x,
+                shape.pos.y,
+                shape.rot,
+                shape.s,
+                shape.s_rot,
+                shape.spin,
+                shape.scale,
+            ] {
+                hash.add(value.to_bits());
+            }
+            hash.add(shape.bound as u64);
+        };
+        for ship in &self.ships {
+            add_shape(&mut hash, &ship.shape);
+            for value in [
+                ship.alive as u64,
+                ship.score as u64,
+                ship.spare as u64,
+                ship.fire_delay as u64,
+                ship.respawn as u64,
+                ship.shield as u64,
+                ship.hyperspace as u64,
+            ] {
+                hash.add(value);
+            }
+        }
+        for asteroid in self.asteroids.values() {
+            add_shape(&mut hash, asteroid);
+        }
+        for bullet in self.bullets.values() {
+            add_shape(&mut hash, bullet);
+        }
+        hash.add(self.spare as u64);
+        hash.add(self.rng.clone().gen());
+        hash.0
+    }
+    /// True once every ship is destroyed for good.
+    pub fn over(&self) -> bool {
+        self.ships
+            .iter()
+            .all(|ship| !ship.alive && ship.respawn == 0)
+    }
+    /// Sends in a new asteroid from outside the playfield.
+    pub fn spawn_asteroid(&mut self) -> Handle {
+        let asteroid = create_asteroid(&mut self.rng, 0.0, self.bounds.x, 0.0, self.bounds.y);
+        self.asteroids.insert(asteroid)
+    }
+    /// Advances the game by one frame; `inputs` has one entry per ship, and
+    /// ships without one do nothing.
+    pub fn step(&mut self, inputs: &[ShipInput]) {
+        for i in 0..self.ships.len() {
+            self.steer(i, &inputs.get(i).copied().unwrap_or_default());
+        }
+
+        // A new asteroid drifts in every four seconds, or one per screen of
+        // playfield in a bigger arena.
+        if self.frame % 240 == 180 {
+            let screens = (self.bounds.x * self.bounds.y / (PLAYFIELD.x * PLAYFIELD.y)).round();
+            for _ in 0..(screens as u32).max(1) {
+                self.spawn_asteroid();
+            }
+        }
+
+        for asteroid in self.asteroids.values_mut() {
+            if asteroid.pos.x <= self.bounds.x - asteroid.scale
+                && asteroid.pos.x >= asteroid.scale
+                && asteroid.pos.y <= self.bounds.y - asteroid.scale
+                && asteroid.pos.y >= asteroid.scale
+            {
+                asteroid.bound = true;
+            }
+            asteroid.tick();
+            asteroid.wrap(self.bounds);
+        }
+        for handle in self.bullets.handles() {
+            let bullet = self.bullets.get_mut(handle).unwrap();
+            bullet.s = self.ships[bullet.owner].shape.scale * 0.6;
+            bullet.pos += bullet.velocity();
+            if bullet.pos.x < 0.0
+                || bullet.pos.x > self.bounds.x
+                || bullet.pos.y < 0.0
+                || bullet.pos.y > self.bounds.y
+            {
+                self.bullets.despawn(handle);
+            }
+        }
+        self.bullets.flush();
+
+        self.grid.clear();
+        for (handle, bullet) in self.bullets.iter() {
+            self.grid.insert(handle, bullet.pos);
+        }
+        let scored = collide(
+            &mut self.asteroids,
+            &mut self.bullets,
+            &self.grid,
+            &mut self.rng,
+        );
+        for (owner, points) in scored {
+            self.ships[owner].score += points;
+        }
+        self.asteroids.flush();
+        self.bullets.flush();
+        if self.rules.mode == Mode::Versus {
+            self.shoot_ships();
+        }
+        for i in 0..self.ships.len() {
+            let ship = &self.ships[i];
+            if ship.alive
+                && ship.shield == 0
+                && !self.god
+                && destroy(&self.asteroids, &ship.shape, self.bounds)
+            {
+                self.lose_ship(i);
+            }
+        }
+        let min_scale = self.ships[0].shape.scale / 2.0;
+        self.asteroids.retain(|asteroid| asteroid.scale > min_scale);
+        if let Some(ship) = self.ships.iter().find(|ship| ship.alive) {
+            self.camera
+                .follow(self.bounds, &ship.shape, self.asteroids.values());
+        }
+        self.frame += 1;
+    }
+    // Turns, moves and fires ship `i`, or counts down to its return.
+    fn steer(&mut self, i: usize, input: &ShipInput) {
+        let ship = &mut self.ships[i];
+        if !ship.alive {
+            if ship.respawn > 0 {
+                ship.respawn -= 1;
+                if ship.respawn == 0 {
+                    ship.revive();
+                }
+            }
+            return;
+        }
+        ship.shield = ship.shield.saturating_sub(1);
+        ship.hyperspace = ship.hyperspace.saturating_sub(1);
+        // The jump lands anywhere at all, at rest; on top of an asteroid is
+        // the risk that comes with it.
+        if input.hyperspace && ship.hyperspace == 0 {
+            ship.shape.pos = Vec2::new(
+                self.rng.gen_range(0.0..self.bounds.x),
+                self.rng.gen_range(0.0..self.bounds.y),
+            );
+            ship.shape.s = 0.0;
+            ship.hyperspace = HYPERSPACE_RECHARGE;
+        }
+        ship.shape.direct(input);
+        let period = self.fire_period;
+        if input.fire {
+            if ship.fire_delay == 0 {
+                let bullet = ship.shape.fire(&mut self.rng);
+                self.bullets.insert(bullet);
+                ship.flash(255, 100, 0);
+            } else {
+                ship.flash(
+                    255,
+                    150 + 105 / (period - ship.fire_delay),
+                    255 / (period - ship.fire_delay),
+                );
+            }
+            ship.fire_delay = (ship.fire_delay + 1) % period;
+        } else if ship.fire_delay > 0 {
+            ship.flash(
+                255,
+                150 + 105 / (period - ship.fire_delay),
+                255 / (period - ship.fire_delay),
+            );
+            ship.fire_delay = (ship.fire_delay + 1) % period;
+        }
+        ship.shape.wrap(self.bounds);
+    }
+    // Versus: bullets that reach another ship destroy it and score for the
+    // shooter.
+    fn shoot_ships(&mut self) {
+        for i in 0..self.ships.len() {
+            let ship = &self.ships[i];
+            if !ship.alive || ship.shield > 0 || self.god {
+                continue;
+            }
+            let hit = self.bullets.iter().find(|(_, bullet)| {
+                bullet.owner != i
+                    && bullet.pos.wrapped_distance(ship.shape.pos, self.bounds)
+                        <= ship.shape.scale * 0.8
+            });
+            if let Some((handle, bullet)) = hit {
+                let owner = bullet.owner;
+                self.bullets.remove(handle);
+                self.ships[owner].score += KILL_POINTS;
+                self.lose_ship(i);
+            }
+        }
+    }
+    // Destroys ship `i`, bringing it back later if a spare ship is left.
+    fn lose_ship(&mut self, i: usize) {
+        let spare = if self.rules.shared_lives && self.rules.mode == Mode::Coop {
+            &mut self.spare
+        } else {
+            &mut self.ships[i].spare
+        };
+        let back = *spare > 0;
+        if back {
+            *spare -= 1;
+        }
+        let ship = &mut self.ships[i];
+        ship.alive = false;
+        ship.respawn = if back { RESPAWN } else { 0 };
+    }
+    /// Draws what the camera sees onto a `camera.view` sized screen, then the
+    /// scores and, in a bigger arena, the radar.
+    pub fn draw(&self, r: &mut dyn Renderer) {
+        let view = &mut self.camera.transform(r);
+        for ship in self.ships.iter().filter(|ship| ship.visible()) {
+            ship.shape.draw(self.bounds, &self.camera, view);
+        }
+        for asteroid in self.asteroids.values() {
+            asteroid.draw(self.bounds, &self.camera, view);
+        }
+        for bullet in self.bullets.values() {
+            bullet.draw(self.bounds, &self.camera, view);
+        }
+        if self.camera.follows {
+            self.draw_radar(r);
+        }
+        self.draw_hud(r);
+    }
+    // Each player's score and spare ships, side by side along the top.
+    fn draw_hud(&self, r: &mut dyn Renderer) {
+        let column = self.camera.view.x / self.ships.len() as f64;
+        for (i, ship) in self.ships.iter().enumerate() {
+            let x = 10.0 + i as f64 * column;
+            draw_text(
+                &ship.score.to_string(),
+                Vec2::new(x, 10.0),
+                12.0,
+                ship.color,
+                r,
+            );
+            // A shared pool shows under the first player.
+            let spare = match (self.spare, i) {
+                (0, _) => ship.spare,
+                (pool, 0) => pool,
+                _ => 0,
+            };
+            r.set_color(ship.color);
+            for k in 0..spare.min(10) {
+                let at = Vec2::new(x + 4.0 + k as f64 * 12.0, 36.0);
+                r.polygon(&[
+                    at + Vec2::new(0.0, -6.0),
+                    at + Vec2::new(4.0, 5.0),
+                    at + Vec2::new(0.0, 2.0),
+                    at + Vec2::new(-4.0, 5.0),
+                ]);
+            }
+        }
+    }
+    // The whole playfield shrunk into the corner: a dot per asteroid, the
+    // ships, and the outline of what is on screen.
+    fn draw_radar(&self, r: &mut dyn Renderer) {
+        let view = self.camera.view;
+        let corner = view - Vec2::new(RADAR + 10.0, RADAR + 10.0);
+        let scale = RADAR / self.bounds.x.max(self.bounds.y);
+        let size = self.bounds * scale;
+        let to_radar = |pos: Vec2| corner + pos.wrap(self.bounds) * scale;
+        r.set_color(RADAR_BACK);
+        r.fill_rect(corner, size);
+        r.set_color(RADAR_EDGE);
+        r.polygon(&[
+            corner,
+            corner + Vec2::new(size.x, 0.0),
+            corner + size,
+            corner + Vec2::new(0.0, size.y),
+        ]);
+        r.set_color(RADAR_ASTEROID);
+        for asteroid in self.asteroids.values() {
+            let blip = (asteroid.scale * scale).max(1.0);
+            r.fill_rect(
+                to_radar(asteroid.pos) - Vec2::new(blip, blip) / 2.0,
+                Vec2::new(blip, blip),
+            );
+        }
+        for ship in self.ships.iter().filter(|ship| ship.alive) {
+            r.set_color(ship.color);
+            r.fill_rect(
+                to_radar(ship.shape.pos) - Vec2::new(1.5, 1.5),
+                Vec2::new(3.0, 3.0),
+            );
+        }
+        // The view can straddle the wrap; its outline is clipped to the radar.
+        let min = to_radar(self.camera.center) - self.camera.extent() * scale / 2.0;
+        let max = min + self.camera.extent() * scale;
+        let clamp = |p: Vec2| {
+            Vec2::new(
+                p.x.clamp(corner.x, corner.x + size.x),
+                p.y.clamp(corner.y, corner.y + size.y),
+            )
+        };
+        r.set_color(RADAR_EDGE);
+        r.polygon(&[
+            clamp(min),
+            clamp(Vec2::new(max.x, min.y)),
+            clamp(max),
+            clamp(Vec2::new(min.x, max.y)),
+        ]);
+    }
+}
+
+// 64-bit FNV-1a: tiny, and the same in every build, unlike `DefaultHasher`.
+struct Fnv(u64);
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv(0xcbf29ce484222325)
+    }
+}
+impl Fnv {
+    fn add(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
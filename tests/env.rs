@@ -0,0 +1,38 @@
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+// Talks to `foxtroids-env` the way an agent would.
+#[test]
+fn agents_play_over_stdin() {
+    let mut env = Command::new(env!("CARGO_BIN_EXE_foxtroids-env"))
+        .args(["--nearest", "2", "--pixels", "16x12", "--frame-skip", "2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = env.stdin.take().unwrap();
+    let mut output = BufReader::new(env.stdout.take().unwrap()).lines();
+    let mut ask = |request: Value| -> Value {
+        writeln!(&input, "{}", request).unwrap();
+        serde_json::from_str(&output.next().unwrap().unwrap()).unwrap()
+    };
+    let spec = ask(json!({"op": "spec"}));
+    assert_eq!(spec["features"], 18);
+    assert!(ask(json!({"op": "step", "action": 0}))["error"].is_string());
+    let reset = ask(json!({"op": "reset", "seed": 5}));
+    assert_eq!(reset["obs"]["features"].as_array().unwrap().len(), 18);
+    assert_eq!(reset["obs"]["pixels"].as_array().unwrap().len(), 16 * 12);
+    assert!(reset.get("reward").is_none());
+    let step = ask(json!({"op": "step", "action": {"fire": true, "left": true}}));
+    assert_eq!(step["info"]["frame"], 2);
+    assert_eq!(step["done"], false);
+    // Bits and names are the same buttons.
+    let bits = ask(json!({"op": "step", "action": 0b10001}));
+    ask(json!({"op": "reset", "seed": 5}));
+    ask(json!({"op": "step", "action": 0b10001}));
+    assert_eq!(ask(json!({"op": "step", "action": 0b10001})), bits);
+    assert!(ask(json!({"op": "step", "action": {"jump": true}}))["error"].is_string());
+    writeln!(&input, "{}", json!({"op": "close"})).unwrap();
+    assert!(env.wait().unwrap().success());
+}
@@ -0,0 +1,87 @@
+//! Renders frames from fixed seeds and replays with the software rasteriser
+//! and compares them against the PNGs in `tests/golden`. Run with
+//! `FOXTROIDS_BLESS=1` to write new golden images after an intended change.
+
+use foxtroids::input::ShipInput;
+use foxtroids::render::software::Framebuffer;
+use foxtroids::render::{Color, Renderer};
+use foxtroids::replay::Replay;
+use foxtroids::world::{World, PLAYFIELD};
+use std::path::PathBuf;
+
+// A pixel differs when a channel is off by more than this...
+const CHANNEL_TOLERANCE: u8 = 8;
+// ...and an image fails when more than this share of its pixels differ.
+const PIXEL_TOLERANCE: f64 = 0.001;
+
+fn render(world: &World) -> Framebuffer {
+    let mut fb = Framebuffer::new(PLAYFIELD.x as usize, PLAYFIELD.y as usize);
+    fb.clear(Color::BLACK);
+    world.draw(&mut fb);
+    fb
+}
+
+fn check(name: &str, frame: &Framebuffer) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name));
+    if std::env::var_os("FOXTROIDS_BLESS").is_some() {
+        frame.save_png(&path).unwrap();
+        return;
+    }
+    let golden = Framebuffer::load_png(&path).unwrap_or_else(|error| {
+        panic!(
+            "{}: {} (run with FOXTROIDS_BLESS=1 to create it)",
+            path.display(),
+            error
+        )
+    });
+    let differing = frame.diff(&golden, CHANNEL_TOLERANCE);
+    let allowed = (frame.width * frame.height) as f64 * PIXEL_TOLERANCE;
+    if differing as f64 > allowed {
+        let actual = std::env::temp_dir().join(format!("{}.actual.png", name));
+        frame.save_png(&actual).unwrap();
+        panic!(
+            "{}: {} pixels differ from the golden image, see {}",
+            name,
+            differing,
+            actual.display()
+        );
+    }
+}
+
+fn idle(seed: u64, frames: u64) -> World {
+    let mut world = World::new(PLAYFIELD, seed);
+    for _ in 0..frames {
+        world.step(&[ShipInput::default()]);
+    }
+    world
+}
+
+#[test]
+fn fresh_game() {
+    check("seed_1_start", &render(&idle(1, 1)));
+}
+
+#[test]
+fn asteroids_drifting_in() {
+    check("seed_7_idle_600", &render(&idle(7, 600)));
+}
+
+#[test]
+fn replayed_dogfight() {
+    let replay = Replay::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/replays/dogfight.replay"
+    ))
+    .unwrap();
+    let mut world = replay.world();
+    for (frame, input) in replay.inputs.iter().enumerate() {
+        world.step(input);
+        if frame == 399 {
+            check("dogfight_400", &render(&world));
+        }
+    }
+    assert!(world.score() > 0);
+    check("dogfight_end", &render(&world));
+}
@@ -0,0 +1,96 @@
+use foxtroids::input::ShipInput;
+use foxtroids::net::{Conditions, LossyTransport, NetConfig, NetSession, UdpTransport};
+use foxtroids::settings::{Mode, Rules};
+use foxtroids::world::World;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+const FRAMES: u64 = 300;
+
+// One session per player, talking over loopback UDP through `conditions`.
+// `seeds` gives each peer's game seed; they should all be equal.
+fn peers(seeds: &[u64], conditions: &Conditions) -> Vec<NetSession> {
+    let sockets: Vec<UdpSocket> = seeds
+        .iter()
+        .map(|_| UdpSocket::bind("127.0.0.1:0").unwrap())
+        .collect();
+    let addresses: Vec<_> = sockets.iter().map(|s| s.local_addr().unwrap()).collect();
+    let rules = Rules {
+        players: seeds.len(),
+        mode: Mode::Versus,
+        lives: 3,
+        ..Rules::default()
+    };
+    sockets
+        .into_iter()
+        .zip(seeds)
+        .enumerate()
+        .map(|(local, (socket, seed))| {
+            let udp = UdpTransport::new(socket, addresses.clone()).unwrap();
+            let transport = LossyTransport::new(udp, conditions.clone(), local as u64);
+            let world = World::with_rules(rules.clone(), *seed);
+            NetSession::new(world, local, Box::new(transport), NetConfig::default())
+        })
+        .collect()
+}
+
+// Ticks every peer with random buttons until all have confirmed `FRAMES`
+// frames or one finds a desync.
+fn play(sessions: &mut [NetSession]) {
+    let mut rng = StdRng::seed_from_u64(11);
+    let started = Instant::now();
+    let done = |session: &NetSession| {
+        session.confirmed().min(session.world().frame) > FRAMES || session.desync().is_some()
+    };
+    while !sessions.iter().all(done) {
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "netplay stalled"
+        );
+        for session in sessions.iter_mut() {
+            let input = ShipInput::from_bits(rng.gen::<u8>() & 0b11111);
+            session.tick(input).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn peers_agree_over_a_bad_network() {
+    let conditions = Conditions {
+        loss: 0.2,
+        latency: Duration::from_millis(8),
+        jitter: Duration::from_millis(6),
+    };
+    for players in [2, 4] {
+        let mut sessions = peers(&vec![9; players], &conditions);
+        play(&mut sessions);
+        // Every peer ended up with the same inputs and the same world as an
+        // offline run of them.
+        let replay = sessions[0].replay();
+        let mut world = replay.world();
+        for inputs in &replay.inputs[..FRAMES as usize] {
+            world.step(inputs);
+        }
+        for session in &sessions {
+            assert_eq!(session.desync(), None);
+            assert!(session.rollbacks > 0);
+            assert_eq!(session.checksum(FRAMES), Some(world.checksum()));
+            assert_eq!(
+                session.replay().inputs[..FRAMES as usize],
+                replay.inputs[..FRAMES as usize]
+            );
+        }
+    }
+}
+
+#[test]
+fn different_games_are_caught() {
+    let mut sessions = peers(&[1, 2], &Conditions::default());
+    play(&mut sessions);
+    let desync = sessions[0].desync().unwrap();
+    assert_eq!(desync.frame, 0);
+    assert_eq!(desync.player, 1);
+}
@@ -0,0 +1,99 @@
+use foxtroids::input::ShipInput;
+use foxtroids::net::{Broadcaster, Spectator};
+use foxtroids::settings::{Mode, Rules};
+use foxtroids::world::World;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::Write;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+const FRAMES: u64 = 1500;
+const LATE: u64 = 700;
+
+// Every frame a spectator shows must match the player's world at that frame.
+fn check(spectator: &Spectator, checksums: &[u64]) -> Option<u64> {
+    let world = spectator.world()?;
+    assert_eq!(world.checksum(), checksums[world.frame as usize]);
+    Some(world.frame)
+}
+
+#[test]
+fn spectators_follow_and_join_late() {
+    let rules = Rules {
+        players: 2,
+        mode: Mode::Versus,
+        ..Rules::default()
+    };
+    let mut world = World::with_rules(rules, 4);
+    let mut broadcaster = Broadcaster::bind("127.0.0.1:0").unwrap();
+    let address = broadcaster.local_addr().unwrap();
+    let mut early = Spectator::connect(address, 10).unwrap();
+    let mut late = None;
+    let mut late_first = None;
+    let mut checksums = vec![world.checksum()];
+    let mut rng = StdRng::seed_from_u64(8);
+    while world.frame < FRAMES {
+        if world.frame == LATE {
+            late = Some(Spectator::connect(address, 10).unwrap());
+        }
+        let inputs: Vec<ShipInput> = (0..2)
+            .map(|_| ShipInput::from_bits(rng.gen::<u8>() & 0b11111))
+            .collect();
+        broadcaster.frame(&world, &inputs).unwrap();
+        world.step(&inputs);
+        checksums.push(world.checksum());
+        early.tick().unwrap();
+        check(&early, &checksums);
+        if let Some(late) = &mut late {
+            late.tick().unwrap();
+            late_first = late_first.or(check(late, &checksums));
+        }
+    }
+    assert_eq!(broadcaster.spectators(), 2);
+    broadcaster.finish(Duration::from_secs(5));
+
+    let mut late = late.unwrap();
+    let started = Instant::now();
+    while !(early.ended() && late.ended()) {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "stream stalled"
+        );
+        early.tick().unwrap();
+        late.tick().unwrap();
+        late_first = late_first.or(check(&late, &checksums));
+    }
+    for spectator in [&early, &late] {
+        assert_eq!(check(spectator, &checksums), Some(FRAMES));
+    }
+    // The late spectator started from the keyframe before it joined, not
+    // from the beginning.
+    assert!(late_first.unwrap() >= 600);
+}
+
+// A spectator fed `message` after the stream's magic fails rather than
+// overflowing or waiting to buffer it all.
+fn rejects(message: &[u8]) -> bool {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut spectator = Spectator::connect(listener.local_addr().unwrap(), 10).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.write_all(b"FXS1").unwrap();
+    stream.write_all(message).unwrap();
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(5) {
+        if spectator.tick().is_err() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn spectators_reject_huge_lengths_and_the_last_frame() {
+    assert!(rejects(&[b'K', 0xff, 0xff, 0xff, 0xff]));
+    let mut inputs = vec![b'I', 8, 0, 0, 0];
+    inputs.extend_from_slice(&u64::MAX.to_le_bytes());
+    assert!(rejects(&inputs));
+}
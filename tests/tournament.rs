@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use foxtroids::tournament::Ratings;
+use std::process::Command;
+
+// A shell agent that spins and fires plays the built-in pilot and an idle
+// ship, and everyone ends up rated.
+#[test]
+fn tournaments_rate_built_in_and_external_entrants() {
+    let dir = std::env::temp_dir().join(format!("foxtroids-tournament-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let agent = dir.join("spin.sh");
+    std::fs::write(
+        &agent,
+        r#"echo '{"op": "reset"}'
+while read line; do echo '{"op": "step", "action": {"left": true, "fire": true}}'; done
+"#,
+    )
+    .unwrap();
+    let ratings = dir.join("test.ratings");
+    let output = Command::new(env!("CARGO_BIN_EXE_foxtroids-tournament"))
+        .args([
+            "--seeds",
+            "4",
+            "--modes",
+            "coop",
+            "--frames",
+            "1200",
+            "--ratings",
+        ])
+        .arg(&ratings)
+        .args([
+            "pilot",
+            "idle",
+            &format!("spinner=exec:sh {}", agent.display()),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let printed = String::from_utf8(output.stdout).unwrap();
+    // Every pair from both sides, then the leaderboard and the breakdown.
+    assert_eq!(
+        printed
+            .lines()
+            .filter(|line| line.starts_with("coop seed 4:"))
+            .count(),
+        6
+    );
+    assert!(printed.contains("rank  name"));
+    assert!(printed.lines().any(|line| line.starts_with("points ")));
+    let rated = Ratings::load(&ratings).unwrap();
+    let names: Vec<&str> = rated.0.iter().map(|rating| rating.name.as_str()).collect();
+    assert_eq!(names, ["pilot", "spinner", "idle"]);
+    assert!(rated.0.iter().all(|rating| rating.games == 4));
+    assert_eq!(rated.0[1].wins, 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+// Unknown or valueless options and names a ratings file would drop are
+// refused before anything is played.
+#[test]
+fn tournaments_refuse_bad_options_and_names() {
+    for args in [
+        &["--sedes", "4", "pilot", "idle"][..],
+        &["pilot", "idle", "--frames"],
+        &["#pilot=pilot", "idle"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_foxtroids-tournament"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(output.stdout.is_empty());
+    }
+}
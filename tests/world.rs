@@ -0,0 +1,110 @@
+use foxtroids::input::ShipInput;
+use foxtroids::math::Vec2;
+use foxtroids::render::software::Framebuffer;
+use foxtroids::render::Color;
+use foxtroids::settings::{Mode, Rules};
+use foxtroids::world::{World, HYPERSPACE_RECHARGE, PLAYFIELD};
+
+// Thrusts, turns and fires in a repeating pattern.
+fn scripted(frame: u64) -> ShipInput {
+    ShipInput {
+        left: frame % 90 < 20,
+        right: frame % 150 > 120,
+        thrust: frame % 60 < 30,
+        reverse: false,
+        fire: frame.is_multiple_of(7),
+        hyperspace: false,
+    }
+}
+
+#[test]
+fn same_seed_and_input_give_the_same_game() {
+    let bounds = Vec2::new(600.0, 600.0);
+    let mut a = World::new(bounds, 42);
+    let mut b = World::new(bounds, 42);
+    for frame in 0..2000 {
+        a.step(&[scripted(frame)]);
+        b.step(&[scripted(frame)]);
+        assert_eq!(a.ships[0].shape.pos, b.ships[0].shape.pos);
+        assert_eq!(a.score(), b.score());
+        assert_eq!(a.over(), b.over());
+    }
+    let positions = |w: &World| w.asteroids.values().map(|s| s.pos).collect::<Vec<_>>();
+    assert_eq!(positions(&a), positions(&b));
+    assert!(!a.asteroids.is_empty());
+}
+
+#[test]
+fn world_draws_into_a_framebuffer() {
+    let mut world = World::new(Vec2::new(200.0, 200.0), 7);
+    world.step(&[ShipInput::default()]);
+    let mut fb = Framebuffer::new(200, 200);
+    world.draw(&mut fb);
+    // The ship sits in the middle of the playfield.
+    let lit = (80..120)
+        .flat_map(|x| (80..120).map(move |y| (x, y)))
+        .filter(|&(x, y)| fb.pixel(x, y) != Color::BLACK)
+        .count();
+    assert!(lit > 10);
+}
+
+// Two ships side by side; the first turns to face the second and fires.
+fn duel(mode: Mode) -> World {
+    let rules = Rules {
+        players: 2,
+        mode,
+        lives: 2,
+        ..Rules::default()
+    };
+    let mut world = World::with_rules(rules, 3);
+    world.ships[0].shape.rot = 0.0;
+    let fire = ShipInput {
+        fire: true,
+        ..ShipInput::default()
+    };
+    world.step(&[fire, ShipInput::default()]);
+    for _ in 0..30 {
+        world.step(&[]);
+    }
+    world
+}
+
+#[test]
+fn versus_bullets_hit_other_ships() {
+    let mut world = duel(Mode::Versus);
+    assert!(!world.ships[1].alive);
+    assert_eq!(world.ships[0].score, 100);
+    assert_eq!(world.ships[1].spare, 0);
+    // The spare ship comes back where the first one started.
+    for _ in 0..120 {
+        world.step(&[]);
+    }
+    assert!(world.ships[1].alive);
+    assert_eq!(world.ships[1].shape.pos, world.ships[1].spawn);
+    assert!(!world.over());
+}
+
+#[test]
+fn coop_bullets_pass_through() {
+    let world = duel(Mode::Coop);
+    assert!(world.ships.iter().all(|ship| ship.alive));
+    assert_eq!(world.score(), 0);
+}
+
+#[test]
+fn hyperspace_jumps_then_recharges() {
+    let mut world = World::new(PLAYFIELD, 3);
+    let jump = ShipInput {
+        hyperspace: true,
+        ..ShipInput::default()
+    };
+    world.step(&[jump]);
+    let landed = world.ships[0].shape.pos;
+    assert_ne!(landed, world.ships[0].spawn);
+    for _ in 1..HYPERSPACE_RECHARGE {
+        world.step(&[jump]);
+        assert_eq!(world.ships[0].shape.pos, landed);
+    }
+    world.step(&[jump]);
+    assert_ne!(world.ships[0].shape.pos, landed);
+}